@@ -0,0 +1,114 @@
+//! Run the ring on a matrix of builder configurations for the multithreaded executors.
+//! See `TokioConfig` and `PoolConfig` for the options.
+//!
+use
+{
+	executor_benchmarks :: * ,
+	criterion           :: { Criterion, BenchmarkId, criterion_group                 } ,
+	async_executors     :: { *                                                      } ,
+	std                 :: { convert::TryFrom                                       } ,
+	futures::executor   :: { block_on                                               } ,
+};
+
+
+const NODES  : usize   = 100;
+const FORWARD: Forward = Forward::Detach;
+const SAMPLES: usize   = 30;
+
+
+fn config( c: &mut Criterion )
+{
+	let pinning = Pinning::from_env();
+	pinning.pin_driver();
+
+	let mut group = c.benchmark_group( format!( "Config {} {}", FORWARD, NODES ) );
+
+	group.sample_size( SAMPLES );
+
+
+	for config in TokioConfig::matrix()
+	{
+		let id = bench_id( "tokio::runtime::Runtime", "TokioTpNativeRing", &config );
+
+		group.bench_with_input( BenchmarkId::new( "tokio::runtime::Runtime", config ), &config, |b, config|
+		{
+			let mut builder = config.builder();
+			pinning.tokio( &mut builder, config.core_threads );
+
+			let mut pool = builder.build().expect( "build tokio threadpool" );
+
+			b.iter_custom( |iters| timed( &id, iters, ||
+			{
+				let mut ring = TokioTpNativeRing::new( NODES, FORWARD );
+
+				pool.block_on( ring.run( pool.handle().clone() ) );
+			}));
+		});
+
+		keep_last( &id, SAMPLES );
+
+
+		let id = bench_id( "TokioTp", "BoundedRing", &config );
+
+		group.bench_with_input( BenchmarkId::new( "TokioTp Spawn", config ), &config, |b, config|
+		{
+			let mut builder = config.builder();
+			pinning.tokio( &mut builder, config.core_threads );
+
+			let mut pool = TokioTp::try_from( &mut builder ).expect( "build tokio threadpool" );
+
+			b.iter_custom( |iters| timed( &id, iters, ||
+			{
+				let mut ring = BoundedRing::new( NODES, FORWARD );
+
+				pool.block_on( ring.run( pool.clone() ) );
+			}));
+		});
+
+		keep_last( &id, SAMPLES );
+	}
+
+
+	for config in PoolConfig::matrix()
+	{
+		let id = bench_id( "ThreadPool", "BoundedRing", &config );
+
+		group.bench_with_input( BenchmarkId::new( "ThreadPool spawn", config ), &config, |b, config|
+		{
+			let mut builder = config.builder();
+			pinning.pool( &mut builder );
+
+			let pool = builder.create().expect( "create threadpool" );
+
+			b.iter_custom( |iters| timed( &id, iters, ||
+			{
+				let mut ring = BoundedRing::new( NODES, FORWARD );
+
+				block_on( ring.run( pool.clone() ) );
+			}));
+		});
+
+		keep_last( &id, SAMPLES );
+	}
+
+
+	group.finish();
+}
+
+fn bench_id( executor: &str, workload: &str, config: &impl std::fmt::Display ) -> BenchId
+{
+	BenchId::new( format!( "Config {} {}", FORWARD, NODES ), executor, workload ).param( "config", config )
+}
+
+
+criterion_group!(benches, config);
+
+
+fn main()
+{
+	benches();
+
+	Criterion::default().configure_from_args().final_summary();
+
+	std::process::exit( report_and_export( "config", &results() ) );
+}
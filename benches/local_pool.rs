@@ -0,0 +1,71 @@
+//! Compare the different ways of driving a futures `LocalPool`. See `Drive`.
+//!
+use
+{
+	executor_benchmarks :: * ,
+	criterion           :: { Criterion, criterion_group                 } ,
+	futures::executor   :: { LocalPool                                  } ,
+};
+
+
+
+fn local_pool( c: &mut Criterion )
+{
+	let pinning = Pinning::from_env();
+	pinning.pin_driver();
+
+	let mut group = c.benchmark_group( "LocalPool drive" );
+
+	for nodes in [10, 100, 200].iter()
+	{
+		let samples = match nodes
+		{
+			10  => 100 ,
+			100 => 50  ,
+			200 => 30  ,
+			_   => unreachable!(),
+		};
+
+		group.sample_size( samples );
+
+
+		for forward in Forward::ALL.iter()
+		{
+			for drive in Drive::ALL.iter()
+			{
+				let id = BenchId::new( "LocalPool drive", "LocalPool", "LocalRing" )
+
+					.param( "drive"  , drive   )
+					.param( "forward", forward )
+					.param( "nodes"  , nodes   )
+				;
+
+				group.bench_function( format!( "LocalPool {} {} {}", drive, forward, &nodes ), |b|
+				{
+					let mut pool = LocalPool::new();
+
+					b.iter_custom( |iters| timed( &id, iters, ||
+					{
+						let mut ring = LocalRing::new( *nodes, *forward );
+
+						drive.run( &mut pool, move |spawner| async move { ring.run( spawner ).await; } );
+					}));
+				});
+
+				keep_last( &id, samples );
+			}
+		}
+	}
+}
+
+criterion_group!(benches, local_pool);
+
+
+fn main()
+{
+	benches();
+
+	Criterion::default().configure_from_args().final_summary();
+
+	std::process::exit( report_and_export( "local_pool", &results() ) );
+}
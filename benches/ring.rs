@@ -1,222 +1,77 @@
 use
 {
 	executor_benchmarks :: * ,
-	criterion           :: { Criterion, criterion_group, criterion_main } ,
-	async_executors     :: { *                                          } ,
-	tokio               :: { runtime::Builder, task::LocalSet           } ,
-	std                 :: { convert::TryFrom                           } ,
-	futures::executor   :: { LocalPool, ThreadPool, block_on            } ,
+	criterion           :: { Criterion, BenchmarkGroup, criterion_group, measurement::WallTime } ,
+	std                 :: { env                                                              } ,
 };
 
 
 
+// Runs the scenario in the file given by `BENCH_SCENARIO`, or `scenarios/ring.yml` by default.
+//
 fn ring( c: &mut Criterion )
 {
 	// let _ = flexi_logger::Logger::with_str( "warn, executor_benchmarks=trace" ).start();
 
+	let path     = env::var( SCENARIO_VAR ).unwrap_or_else( |_| DEFAULT_SCENARIO.to_string() );
+	let scenario = Scenario::load( &path ).unwrap_or_else( |e| panic!( "load scenario: {}", e ) );
 
-	let mut group = c.benchmark_group( "Ring" );
+	let pinning = Pinning::from_env();
+	pinning.pin_driver();
 
-	for nodes in [10, 100, 200].iter()
-	{
-		match nodes
-		{
-			10  => { group.sample_size( 100 ); }
-			100 => { group.sample_size( 50  ); }
-			200 => { group.sample_size( 30  ); }
-			_   => { unreachable!();           }
-		}
-
-
-		group.bench_function( format!( "LocalPool spawn_local {}", &nodes ), |b|
-		{
-			let mut pool     = LocalPool::new();
-			let     spawner  = pool.spawner();
-
-			b.iter( ||
-			{
-				let mut ring = LocalRing::new( *nodes );
-
-				pool.run_until( ring.run( spawner.clone() ) );
-			});
-		});
-
-
-		group.bench_function( format!( "LocalPool spawn_handle_local {}", &nodes ), |b|
-		{
-			let mut pool     = LocalPool::new();
-			let     spawner  = pool.spawner();
-
-			b.iter( ||
-			{
-				let mut ring = LocalHandleRing::new( *nodes );
-
-				pool.run_until( ring.run( spawner.clone() ) );
-			});
-		});
-
-
-		group.bench_function( format!( "tokio::runtime::Runtime (basic_scheduler + LocalSet) {}", &nodes ), |b|
-		{
-			let mut pool = Builder::new().basic_scheduler().build().expect( "build tokio threadpool" );
-
-			b.iter( ||
-			{
-				let exec = LocalSet::new();
-				let mut ring = TokioCtNativeRing::new( *nodes );
-
-				exec.spawn_local( async move { ring.run().await; } );
-				pool.block_on( exec );
-			});
-		});
-
-
-		group.bench_function( format!( "TokioCt spawn_local {}", &nodes ), |b|
-		{
-			let     pool  = TokioCt::try_from( &mut Builder::new() ).expect( "build tokio basic_scheduler" );
-			let mut pool2 = pool.clone();
-
-			b.iter( ||
-			{
-				let pool3 = pool.clone();
-
-				let bench = async move
-				{
-					let mut ring = LocalRing::new( *nodes );
-					ring.run( pool3 ).await;
-				};
-
-				pool2.block_on( bench );
-			});
-		});
-
-
-		group.bench_function( format!( "TokioCt spawn_handle_local {}", &nodes ), |b|
-		{
-			let     pool  = TokioCt::try_from( &mut Builder::new() ).expect( "build tokio basic_scheduler" );
-			let mut pool2 = pool.clone();
-
-			b.iter( ||
-			{
-				let pool3 = pool.clone();
-
-				let bench = async move
-				{
-					let mut ring = LocalHandleRing::new( *nodes );
-					ring.run( pool3 ).await;
-				};
-
-				pool2.block_on( bench );
-			});
-		});
-
-
-		group.bench_function( format!( "ThreadPool spawn {}", &nodes ), |b|
-		{
-			let pool = ThreadPool::new().expect( "create threadpool" );
-
-			b.iter( move ||
-			{
-				let pool2 = pool.clone();
-
-				let mut ring = BoundedRing::new( *nodes );
-
-				block_on( ring.run( pool2 ) );
-			});
-		});
-
-
-		group.bench_function( format!( "ThreadPool spawn_handle {}", &nodes ), |b|
-		{
-			let pool = ThreadPool::new().expect( "create threadpool" );
-
-			b.iter( move ||
-			{
-				let pool2 = pool.clone();
-
-				let mut ring = HandleRing::new( *nodes );
-
-				block_on( ring.run( pool2 ) );
-			});
-		});
+	let mut group = c.benchmark_group( scenario.group.as_str() );
 
+	for entry in scenario.entries()
+	{
+		let Entry { id, executor, workload, config, threads, samples } = entry;
 
-		group.bench_function( format!( "tokio::runtime::Runtime {}", &nodes ), |b|
+		if !threads_fit( &executor, threads )
 		{
-			let mut pool = Builder::new().threaded_scheduler().build().expect( "build tokio threadpool" );
-
-			b.iter( ||
-			{
-				let mut ring = TokioTpNativeRing::new( *nodes );
-
-				pool.block_on( ring.run( pool.handle().clone() ) );
-			});
-		});
-
+			eprintln!( "Skipping {}: the async-std runtime already runs {} threads.", id, async_std_threads() );
+			continue;
+		}
 
-		group.bench_function( format!( "TokioTp Spawn {}", &nodes ), |b|
+		bench( &mut group, id, samples, ||
 		{
-			let mut pool = TokioTp::try_from( &mut Builder::new() ).expect( "build tokio threadpool" );
-
-			b.iter( ||
-			{
-				let mut ring = BoundedRing::new( *nodes );
-
-				pool.block_on( ring.run( pool.clone() ) );
-			});
+			routine( &executor, &workload, config, threads, &pinning ).expect( "validated routine" )
 		});
+	}
 
+	group.finish();
+}
 
-		group.bench_function( format!( "TokioTp SpawnHandle {}", &nodes ), |b|
-		{
-			let mut pool = TokioTp::try_from( &mut Builder::new() ).expect( "build tokio threadpool" );
-
-			b.iter( ||
-			{
-				let mut ring = HandleRing::new( *nodes );
-
-				pool.block_on( ring.run( pool.clone() ) );
-			});
-		});
 
+// Benchmark the routine returned by `setup` and record it's samples for the overhead report.
+// The routine is one iteration of the benchmark.
+//
+fn bench<R: FnMut()>( group: &mut BenchmarkGroup<'_, WallTime>, id: BenchId, samples: usize, setup: impl FnOnce() -> R )
+{
+	let mut setup   = Some( setup );
+	let mut routine = None;
 
-		group.bench_function( format!( "async_std::task::spawn {}", &nodes ), |b|
-		{
-			b.iter( ||
-			{
-				let mut ring = AsyncStdNativeRing::new( *nodes );
+	group.sample_size( samples );
 
-				async_std::task::block_on( ring.run() );
-			});
-		});
+	group.bench_function( id.to_string(), |b|
+	{
+		// Criterion calls this for every sample, but we only want to create the executor once.
+		//
+		let routine = routine.get_or_insert_with( || ( setup.take().expect( "setup runs once" ) )() );
 
+		b.iter_custom( |iters| timed( &id, iters, &mut *routine ) );
+	});
 
-		group.bench_function( format!( "AsyncStd Spawn {}", &nodes ), |b|
-		{
-			let pool = AsyncStd::default();
+	keep_last( &id, samples );
+}
 
-			b.iter( ||
-			{
-				let mut ring = BoundedRing::new( *nodes );
 
-				AsyncStd::block_on( ring.run( pool ) );
-			});
-		});
+criterion_group!(benches, ring);
 
 
-		group.bench_function( format!( "AsyncStd SpawnHandle {}", &nodes ), |b|
-		{
-			let pool = AsyncStd::default();
+fn main()
+{
+	benches();
 
-			b.iter( ||
-			{
-				let mut ring = HandleRing::new( *nodes );
+	Criterion::default().configure_from_args().final_summary();
 
-				AsyncStd::block_on( ring.run( pool ) );
-			});
-		});
-	}
+	std::process::exit( report_and_export( "ring", &results() ) );
 }
-
-criterion_group!(benches, ring);
-criterion_main! (benches);
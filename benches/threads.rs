@@ -0,0 +1,142 @@
+//! Sweep the number of worker threads of the multithreaded executors, so pools can be sized
+//! based on data. Throughput is reported in messages forwarded per second.
+//!
+use
+{
+	executor_benchmarks :: * ,
+	criterion           :: { Criterion, BenchmarkId, Throughput, criterion_group                 } ,
+	async_executors     :: { *                                                                  } ,
+	tokio               :: { runtime::Builder                                                   } ,
+	std                 :: { convert::TryFrom                                                   } ,
+	futures::executor   :: { ThreadPool, block_on                                               } ,
+};
+
+
+const NODES  : usize   = 100;
+const FORWARD: Forward = Forward::Detach;
+const SAMPLES: usize   = 50;
+
+
+fn threads( c: &mut Criterion )
+{
+	let pinning = Pinning::from_env();
+	pinning.pin_driver();
+
+	let mut group = c.benchmark_group( format!( "Threads {} {}", FORWARD, NODES ) );
+
+	group.sample_size( SAMPLES );
+
+	// Each node's message goes round the ring once.
+	//
+	group.throughput( Throughput::Elements( ( NODES * NODES ) as u64 ) );
+
+
+	for threads in thread_counts()
+	{
+		let id = bench_id( "ThreadPool", "BoundedRing", threads );
+
+		group.bench_with_input( BenchmarkId::new( "ThreadPool spawn", threads ), &threads, |b, threads|
+		{
+			let mut builder = ThreadPool::builder();
+			builder.pool_size( *threads );
+			pinning.pool( &mut builder );
+
+			let pool = builder.create().expect( "create threadpool" );
+
+			b.iter_custom( |iters| timed( &id, iters, ||
+			{
+				let mut ring = BoundedRing::new( NODES, FORWARD );
+
+				block_on( ring.run( pool.clone() ) );
+			}));
+		});
+
+		keep_last( &id, SAMPLES );
+
+
+		let id = bench_id( "tokio::runtime::Runtime", "TokioTpNativeRing", threads );
+
+		group.bench_with_input( BenchmarkId::new( "tokio::runtime::Runtime", threads ), &threads, |b, threads|
+		{
+			let mut builder = Builder::new();
+			builder.threaded_scheduler().core_threads( *threads );
+			pinning.tokio( &mut builder, Some( *threads ) );
+
+			let mut pool = builder.build().expect( "build tokio threadpool" );
+
+			b.iter_custom( |iters| timed( &id, iters, ||
+			{
+				let mut ring = TokioTpNativeRing::new( NODES, FORWARD );
+
+				pool.block_on( ring.run( pool.handle().clone() ) );
+			}));
+		});
+
+		keep_last( &id, SAMPLES );
+
+
+		let id = bench_id( "TokioTp", "BoundedRing", threads );
+
+		group.bench_with_input( BenchmarkId::new( "TokioTp Spawn", threads ), &threads, |b, threads|
+		{
+			let mut builder = Builder::new();
+			builder.threaded_scheduler().core_threads( *threads );
+			pinning.tokio( &mut builder, Some( *threads ) );
+
+			let mut pool = TokioTp::try_from( &mut builder ).expect( "build tokio threadpool" );
+
+			b.iter_custom( |iters| timed( &id, iters, ||
+			{
+				let mut ring = BoundedRing::new( NODES, FORWARD );
+
+				pool.block_on( ring.run( pool.clone() ) );
+			}));
+		});
+
+		keep_last( &id, SAMPLES );
+	}
+
+
+	// The async-std runtime is global and can only be sized through the environment,
+	// so it gets a single entry per run. See `async_std_threads`. It's worker threads can not
+	// be pinned either, see `start_async_std`.
+	//
+	let id = bench_id( "AsyncStd", "BoundedRing", async_std_threads() );
+
+	group.bench_with_input( BenchmarkId::new( "AsyncStd Spawn", async_std_threads() ), &(), |b, _|
+	{
+		start_async_std( None, &pinning );
+
+		let pool = AsyncStd::default();
+
+		b.iter_custom( |iters| timed( &id, iters, ||
+		{
+			let mut ring = BoundedRing::new( NODES, FORWARD );
+
+			AsyncStd::block_on( ring.run( pool ) );
+		}));
+	});
+
+	keep_last( &id, SAMPLES );
+
+
+	group.finish();
+}
+
+fn bench_id( executor: &str, workload: &str, threads: usize ) -> BenchId
+{
+	BenchId::new( format!( "Threads {} {}", FORWARD, NODES ), executor, workload ).param( "threads", threads )
+}
+
+
+criterion_group!(benches, threads);
+
+
+fn main()
+{
+	benches();
+
+	Criterion::default().configure_from_args().final_summary();
+
+	std::process::exit( report_and_export( "threads", &results() ) );
+}
@@ -0,0 +1,363 @@
+//! Pin the benchmark driver thread and executor worker threads to specific cores, so results
+//! don't depend on where the OS decides to put threads. Only supported on Linux.
+//!
+//! Pinning is enabled by setting `BENCH_PIN_CORES` to a list of cores, eg. `0,2-5`. The driver
+//! thread is pinned to the first core in the list, worker threads are distributed round robin over
+//! the remaining ones. If the list only has one core, everything runs on it.
+//!
+//! Threads inherit the affinity of the thread that starts them. Executors that start threads we can't
+//! pin, SmolTp and the async-std runtime, have to be started with `Pinning::unpinned`, or all their
+//! workers end up on the driver core.
+//!
+use std::{ env, io, sync::{ Arc, Mutex, atomic::{ AtomicUsize, Ordering } } };
+use tokio::runtime::Builder;
+use futures::executor::ThreadPoolBuilder;
+use log::*;
+
+
+// The environment variable holding the list of cores to pin to.
+//
+pub const PIN_CORES_VAR: &str = "BENCH_PIN_CORES";
+
+
+// Cores we can pin to are below this.
+//
+#[ cfg( target_os = "linux" ) ] const MAX_CORES: usize = libc::CPU_SETSIZE as usize;
+#[ cfg( not( target_os = "linux" ) ) ] const MAX_CORES: usize = usize::MAX;
+
+
+// The cores the driver thread could run on before `pin_driver`, empty if it isn't pinned.
+//
+static DRIVER_AFFINITY: Mutex< Vec<usize> > = Mutex::new( Vec::new() );
+
+
+// Which cores to pin threads to. An empty list means threads aren't pinned.
+//
+#[ derive( Clone, Debug, Default, PartialEq, Eq ) ]
+//
+pub struct Pinning
+{
+	cores: Vec<usize>,
+}
+
+
+impl Pinning
+{
+	// Pin to the given cores. The first one is for the driver thread.
+	//
+	pub fn new( cores: Vec<usize> ) -> Self
+	{
+		Self { cores }
+	}
+
+
+	// Read the cores from the `BENCH_PIN_CORES` environment variable. Don't pin if it is not set.
+	//
+	// # Panics
+	//
+	// If the variable can not be parsed. It's better to fail than to silently produce
+	// numbers that aren't reproducible.
+	//
+	pub fn from_env() -> Self
+	{
+		match env::var( PIN_CORES_VAR )
+		{
+			Ok ( list ) => Self::new( parse_cores( &list ).expect( "parse BENCH_PIN_CORES" ) ),
+			Err( _    ) => Self::default(),
+		}
+	}
+
+
+	// Whether threads get pinned at all.
+	//
+	pub fn enabled( &self ) -> bool
+	{
+		!self.cores.is_empty()
+	}
+
+
+	// Pin the current thread to the driver core.
+	//
+	// # Panics
+	//
+	// If the core does not exist.
+	//
+	pub fn pin_driver( &self )
+	{
+		if let Some( core ) = self.cores.first()
+		{
+			let mut saved = DRIVER_AFFINITY.lock().expect( "lock driver affinity" );
+
+			if saved.is_empty()
+			{
+				*saved = affinity().expect( "read driver thread affinity" );
+			}
+
+			pin_current( *core ).expect( "pin driver thread" );
+		}
+	}
+
+
+	// Run `f` on the cores the driver thread had before `pin_driver`, and pin it again afterwards.
+	// Worker threads started by `f` don't inherit the driver core.
+	//
+	pub fn unpinned<T>( &self, f: impl FnOnce() -> T ) -> T
+	{
+		let saved = DRIVER_AFFINITY.lock().expect( "lock driver affinity" ).clone();
+
+		match self.cores.first()
+		{
+			Some( core ) if !saved.is_empty() =>
+			{
+				set_affinity( &saved ).expect( "restore driver thread affinity" );
+
+				let out = f();
+
+				pin_current( *core ).expect( "pin driver thread" );
+
+				out
+			}
+
+			_ => f(),
+		}
+	}
+
+
+	// The core for worker thread number `index`.
+	//
+	pub fn worker_core( &self, index: usize ) -> Option<usize>
+	{
+		match self.cores.len()
+		{
+			0 => None,
+			1 => Some( self.cores[0] ),
+			n => Some( self.cores[ 1 + index % ( n - 1 ) ] ),
+		}
+	}
+
+
+	// Pin the worker threads of a tokio runtime with `core_threads` workers, `None` for tokio's
+	// default of one per core. Tokio does not tell us the index of the worker thread, so we count
+	// them as they start.
+	//
+	// `on_thread_start` also runs for the threads of the blocking pool. The workers are spawned when
+	// the runtime is built, so they are normally the first `core_threads` threads, and only those
+	// are pinned.
+	// The blocking threads come later and would inherit the core of the worker that started them,
+	// so they get the cores the driver had before `pin_driver` instead.
+	//
+	pub fn tokio( &self, builder: &mut Builder, core_threads: Option<usize> )
+	{
+		if !self.enabled() { return }
+
+		let pinning = self.clone();
+		let started = Arc::new( AtomicUsize::new( 0 ) );
+		let workers = core_threads.unwrap_or_else( num_cpus::get );
+
+		builder.on_thread_start( move ||
+		{
+			let index = started.fetch_add( 1, Ordering::SeqCst );
+
+			match index < workers
+			{
+				true  => pinning.pin_worker( index ),
+				false => unpin_current(),
+			}
+		});
+	}
+
+
+	// Pin the worker threads of a futures ThreadPool.
+	//
+	pub fn pool( &self, builder: &mut ThreadPoolBuilder )
+	{
+		if !self.enabled() { return }
+
+		let pinning = self.clone();
+
+		builder.after_start( move |index| pinning.pin_worker( index ) );
+	}
+
+
+	// Don't panic here, since this runs on the executor threads.
+	//
+	fn pin_worker( &self, index: usize )
+	{
+		if let Some( core ) = self.worker_core( index )
+		{
+			if let Err( e ) = pin_current( core )
+			{
+				error!( "Failed to pin worker thread {} to core {}: {}", index, core, e );
+			}
+		}
+	}
+}
+
+
+// Parse a list of cores like `0,2-5`.
+//
+pub fn parse_cores( list: &str ) -> Result< Vec<usize>, String >
+{
+	let mut cores = Vec::new();
+
+	for part in list.split( ',' ).map( str::trim ).filter( |p| !p.is_empty() )
+	{
+		let parse = |s: &str| s.trim().parse::<usize>().map_err( |_| format!( "invalid core: {}", part ) );
+
+		match part.find( '-' )
+		{
+			None      => cores.push( parse( part )? ),
+
+			Some( i ) =>
+			{
+				let ( start, end ) = ( parse( &part[..i] )?, parse( &part[i+1..] )? );
+
+				if start > end
+				{
+					return Err( format!( "invalid range of cores: {}", part ) );
+				}
+
+				cores.extend( start..=end );
+			}
+		}
+	}
+
+	if let Some( core ) = cores.iter().find( |c| **c >= MAX_CORES )
+	{
+		return Err( format!( "core {} is out of range, cores go up to {}", core, MAX_CORES - 1 ) );
+	}
+
+	Ok( cores )
+}
+
+
+// Let the current thread run on the cores the driver thread had before `pin_driver`.
+// Doesn't panic, since this runs on the executor threads.
+//
+fn unpin_current()
+{
+	let saved = DRIVER_AFFINITY.lock().expect( "lock driver affinity" ).clone();
+
+	if saved.is_empty() { return }
+
+	if let Err( e ) = set_affinity( &saved )
+	{
+		error!( "Failed to unpin thread: {}", e );
+	}
+}
+
+
+// Pin the current thread to the given core.
+//
+pub fn pin_current( core: usize ) -> Result<(), io::Error>
+{
+	set_affinity( &[ core ] )
+}
+
+
+// Let the current thread run on the given cores.
+//
+#[ cfg( target_os = "linux" ) ]
+//
+fn set_affinity( cores: &[usize] ) -> Result<(), io::Error>
+{
+	if let Some( core ) = cores.iter().find( |c| **c >= MAX_CORES )
+	{
+		return Err( io::Error::new( io::ErrorKind::InvalidInput, format!( "core {} is out of range", core ) ) );
+	}
+
+	// Safety: cpu_set_t is a plain bitmask, so all zeroes is a valid (empty) set,
+	// the cores are in range and we pass the correct size for it.
+	//
+	unsafe
+	{
+		let mut set: libc::cpu_set_t = std::mem::zeroed();
+
+		for core in cores { libc::CPU_SET( *core, &mut set ); }
+
+		if libc::sched_setaffinity( 0, std::mem::size_of::<libc::cpu_set_t>(), &set ) != 0
+		{
+			return Err( io::Error::last_os_error() );
+		}
+	}
+
+	Ok(())
+}
+
+
+// The cores the current thread can run on.
+//
+#[ cfg( target_os = "linux" ) ]
+//
+fn affinity() -> Result< Vec<usize>, io::Error >
+{
+	// Safety: as in `set_affinity`.
+	//
+	unsafe
+	{
+		let mut set: libc::cpu_set_t = std::mem::zeroed();
+
+		if libc::sched_getaffinity( 0, std::mem::size_of::<libc::cpu_set_t>(), &mut set ) != 0
+		{
+			return Err( io::Error::last_os_error() );
+		}
+
+		Ok( ( 0..MAX_CORES ).filter( |core| libc::CPU_ISSET( *core, &set ) ).collect() )
+	}
+}
+
+
+#[ cfg( not( target_os = "linux" ) ) ]
+//
+fn set_affinity( _cores: &[usize] ) -> Result<(), io::Error>
+{
+	Err( io::Error::new( io::ErrorKind::Other, "pinning threads is only supported on Linux" ) )
+}
+
+
+#[ cfg( not( target_os = "linux" ) ) ]
+//
+fn affinity() -> Result< Vec<usize>, io::Error >
+{
+	Err( io::Error::new( io::ErrorKind::Other, "pinning threads is only supported on Linux" ) )
+}
+
+
+
+#[ cfg( test ) ]
+//
+mod tests
+{
+	use super::*;
+
+	#[test]
+	//
+	fn parse()
+	{
+		assert_eq!( Ok( vec![ 0, 2, 3, 4, 5 ] ), parse_cores( "0,2-5" ) );
+		assert_eq!( Ok( vec![ 1             ] ), parse_cores( " 1 "   ) );
+		assert_eq!( Ok( vec![               ] ), parse_cores( ""      ) );
+
+		assert!( parse_cores( "a"   ).is_err() );
+		assert!( parse_cores( "3-1" ).is_err() );
+
+		#[ cfg( target_os = "linux" ) ]
+		//
+		assert!( parse_cores( &format!( "0-{}", MAX_CORES ) ).is_err() );
+	}
+
+
+	#[test]
+	//
+	fn workers_round_robin()
+	{
+		let pinning = Pinning::new( vec![ 0, 2, 3 ] );
+
+		assert_eq!( Some( 2 ), pinning.worker_core( 0 ) );
+		assert_eq!( Some( 3 ), pinning.worker_core( 1 ) );
+		assert_eq!( Some( 2 ), pinning.worker_core( 2 ) );
+
+		assert_eq!( Some( 5 ), Pinning::new( vec![ 5 ] ).worker_core( 3 ) );
+		assert_eq!( None     , Pinning::default().worker_core( 0 )        );
+	}
+}
@@ -0,0 +1,257 @@
+//! Count allocations, to report them per benchmark iteration next to the timings.
+//!
+//! With the `count_alloc` feature, a global allocator that wraps the system allocator counts every
+//! allocation and the bytes allocated, on all threads. A reallocation counts as an allocation of the
+//! new size. `timed` adds the allocations of every batch to the record of the benchmark.
+//!
+//! It also tracks the bytes that are currently allocated, see `live_bytes`.
+//!
+//! Counting makes every allocation a bit slower, so compare timings of runs with the same features:
+//!
+//! `cargo bench --features count_alloc --bench ring`
+//!
+use crate::Record;
+use serde::{ Deserialize, Serialize };
+use std::{ fmt, ops::Sub, sync::atomic::{ AtomicU64, Ordering::Relaxed } };
+
+#[ cfg( feature = "count_alloc" ) ]
+//
+use std::alloc::{ GlobalAlloc, Layout, System };
+
+
+#[ cfg( feature = "count_alloc" ) ]
+//
+#[ global_allocator ]
+//
+static ALLOCATOR: CountingAlloc = CountingAlloc;
+
+// These stay zero without the feature.
+//
+static COUNT: AtomicU64 = AtomicU64::new( 0 );
+static BYTES: AtomicU64 = AtomicU64::new( 0 );
+static LIVE : AtomicU64 = AtomicU64::new( 0 );
+
+
+#[ cfg( feature = "count_alloc" ) ]
+//
+struct CountingAlloc;
+
+
+#[ cfg( feature = "count_alloc" ) ]
+//
+unsafe impl GlobalAlloc for CountingAlloc
+{
+	// A failed allocation returns null and allocates nothing, so only successful ones are counted.
+	//
+	unsafe fn alloc( &self, layout: Layout ) -> *mut u8
+	{
+		let ptr = System.alloc( layout );
+
+		if !ptr.is_null() { count( layout.size() ); }
+
+		ptr
+	}
+
+
+	unsafe fn alloc_zeroed( &self, layout: Layout ) -> *mut u8
+	{
+		let ptr = System.alloc_zeroed( layout );
+
+		if !ptr.is_null() { count( layout.size() ); }
+
+		ptr
+	}
+
+
+	// When realloc fails, the old block is left as it was, so it's still live.
+	//
+	unsafe fn realloc( &self, ptr: *mut u8, layout: Layout, new_size: usize ) -> *mut u8
+	{
+		let new = System.realloc( ptr, layout, new_size );
+
+		if !new.is_null()
+		{
+			count( new_size );
+			LIVE.fetch_sub( layout.size() as u64, Relaxed );
+		}
+
+		new
+	}
+
+
+	unsafe fn dealloc( &self, ptr: *mut u8, layout: Layout )
+	{
+		LIVE.fetch_sub( layout.size() as u64, Relaxed );
+		System.dealloc( ptr, layout )
+	}
+}
+
+
+#[ cfg( feature = "count_alloc" ) ]
+//
+fn count( size: usize )
+{
+	COUNT.fetch_add( 1          , Relaxed );
+	BYTES.fetch_add( size as u64, Relaxed );
+	LIVE .fetch_add( size as u64, Relaxed );
+}
+
+
+
+// A number of allocations and the bytes they allocated.
+//
+#[ derive( Clone, Copy, Debug, Default, PartialEq, Eq ) ]
+//
+pub struct Allocations
+{
+	pub count: u64,
+	pub bytes: u64,
+}
+
+
+impl Sub for Allocations
+{
+	type Output = Self;
+
+	fn sub( self, other: Self ) -> Self
+	{
+		Self
+		{
+			count: self.count - other.count ,
+			bytes: self.bytes - other.bytes ,
+		}
+	}
+}
+
+
+// All allocations made by the process so far. `None` if the `count_alloc` feature is disabled.
+//
+pub fn allocations() -> Option<Allocations>
+{
+	match cfg!( feature = "count_alloc" )
+	{
+		true  => Some( Allocations { count: COUNT.load( Relaxed ), bytes: BYTES.load( Relaxed ) } ),
+		false => None,
+	}
+}
+
+
+
+// The bytes that are currently allocated by the process. `None` if the `count_alloc` feature is disabled.
+//
+pub fn live_bytes() -> Option<u64>
+{
+	match cfg!( feature = "count_alloc" )
+	{
+		true  => Some( LIVE.load( Relaxed ) ),
+		false => None,
+	}
+}
+
+
+
+// Allocations per iteration of a benchmark.
+//
+#[ derive( Clone, Debug, PartialEq, Serialize, Deserialize ) ]
+//
+pub struct AllocStats
+{
+	pub allocs_per_iter: f64,
+	pub bytes_per_iter : f64,
+}
+
+
+impl AllocStats
+{
+	pub fn new( total: Allocations, iterations: u64 ) -> Self
+	{
+		let iterations = iterations.max( 1 ) as f64;
+
+		Self
+		{
+			allocs_per_iter: total.count as f64 / iterations ,
+			bytes_per_iter : total.bytes as f64 / iterations ,
+		}
+	}
+}
+
+
+
+// Prints a table of the allocations per iteration in a set of records. Records without counted
+// allocations are skipped.
+//
+pub struct AllocReport( pub Vec<( String, AllocStats )> );
+
+
+impl AllocReport
+{
+	pub fn new( records: &[Record] ) -> Self
+	{
+		Self( records.iter().filter_map( |r| Some(( r.id.to_string(), AllocStats::new( r.allocations?, r.iterations ) )) ).collect() )
+	}
+
+
+	pub fn is_empty( &self ) -> bool
+	{
+		self.0.is_empty()
+	}
+}
+
+
+impl fmt::Display for AllocReport
+{
+	fn fmt( &self, f: &mut fmt::Formatter<'_> ) -> fmt::Result
+	{
+		let width = self.0.iter().map( |(id, _)| id.len() ).max().unwrap_or( 0 );
+
+		writeln!( f, "Allocations per iteration:" )?;
+		writeln!( f )?;
+		writeln!( f, "  {:<width$}  {:>12}  {:>14}", "", "allocations", "bytes", width = width )?;
+
+		for (id, a) in &self.0
+		{
+			writeln!( f, "  {:<width$}  {:>12.1}  {:>14.0}", id, a.allocs_per_iter, a.bytes_per_iter, width = width )?;
+		}
+
+		Ok(())
+	}
+}
+
+
+
+#[ cfg( test ) ]
+//
+mod tests
+{
+	use super::*;
+
+	#[test]
+	//
+	fn per_iteration()
+	{
+		let total = Allocations { count: 30, bytes: 3000 } - Allocations { count: 10, bytes: 1000 };
+		let stats = AllocStats::new( total, 4 );
+
+		assert_eq!( 5.0  , stats.allocs_per_iter );
+		assert_eq!( 500.0, stats.bytes_per_iter  );
+	}
+
+
+	#[ cfg( feature = "count_alloc" ) ]
+	//
+	#[test]
+	//
+	fn counts()
+	{
+		let before = allocations().expect( "counting is enabled" );
+		let v      = Vec::<u8>::with_capacity( 100 );
+		let after  = allocations().expect( "counting is enabled" );
+
+		drop( v );
+
+		// Other tests run in parallel, so there might be more.
+		//
+		assert!( ( after - before ).count >= 1   );
+		assert!( ( after - before ).bytes >= 100 );
+	}
+}
@@ -0,0 +1,324 @@
+//! Save the results of a run as a named baseline and compare later runs against it, to catch
+//! regressions between versions of async_executors automatically.
+//!
+//! Baselines are stored as `<bench>/<name>.json` in the baseline directory, in the same format as
+//! the exported results, so every bench and the runner has it's own baseline of a given name. The
+//! directory is `baselines`, unless `BENCH_BASELINE_DIR` is set. It's not under `target`, so
+//! `cargo clean` doesn't throw away the baselines of older versions. After a run, the benches and
+//! the runner:
+//!
+//! - save the results as a baseline if `BENCH_SAVE_BASELINE` is set to it's name,
+//! - compare to the baseline named by `BENCH_BASELINE` and exit with a non-zero code if any
+//!   benchmark got slower by more than `BENCH_THRESHOLD` percent (5 by default).
+//!
+use crate::{ BenchId, Record, Summary, load_results, to_json };
+use std::{ env, fmt, fs, path::PathBuf };
+
+
+pub const SAVE_BASELINE_VAR: &str = "BENCH_SAVE_BASELINE";
+pub const BASELINE_VAR     : &str = "BENCH_BASELINE"     ;
+pub const THRESHOLD_VAR    : &str = "BENCH_THRESHOLD"    ;
+pub const BASELINE_DIR_VAR : &str = "BENCH_BASELINE_DIR" ;
+
+// The default threshold in percent.
+//
+pub const DEFAULT_THRESHOLD: f64 = 5.0;
+
+
+// The directory baselines are stored in.
+//
+pub fn baseline_dir() -> PathBuf
+{
+	env::var_os( BASELINE_DIR_VAR ).map( PathBuf::from ).unwrap_or_else( || PathBuf::from( "baselines" ) )
+}
+
+
+// The file the baseline `name` of `bench` is stored in.
+//
+pub fn baseline_path( bench: &str, name: &str ) -> PathBuf
+{
+	baseline_dir().join( bench ).join( format!( "{}.json", name ) )
+}
+
+
+// Save summaries as a baseline of `bench`, overwriting an existing baseline of it with the same name.
+//
+pub fn save_baseline( bench: &str, name: &str, summaries: &[Summary] ) -> Result<PathBuf, String>
+{
+	let path = baseline_path( bench, name );
+	let dir  = path.parent().expect( "baseline path has a parent" );
+
+	fs::create_dir_all( dir ).map_err( |e| format!( "create {}: {}", dir.display(), e ) )?;
+
+	fs::write( &path, to_json( summaries ) ).map_err( |e| format!( "write {}: {}", path.display(), e ) )?;
+
+	Ok( path )
+}
+
+
+pub fn load_baseline( bench: &str, name: &str ) -> Result< Vec<Summary>, String >
+{
+	load_results( baseline_path( bench, name ) )
+}
+
+
+
+// The change in mean time of a benchmark compared to the baseline.
+//
+#[ derive( Clone, Debug ) ]
+//
+pub struct Change
+{
+	pub id         : BenchId ,
+	pub baseline_ns: f64     ,
+	pub current_ns : f64     ,
+}
+
+
+impl Change
+{
+	// The change in percent, positive means slower.
+	//
+	pub fn percent( &self ) -> f64
+	{
+		( self.current_ns / self.baseline_ns - 1.0 ) * 100.0
+	}
+}
+
+
+// The result of comparing a run to a baseline.
+//
+#[ derive( Clone, Debug ) ]
+//
+pub struct Comparison
+{
+	pub threshold: f64         ,
+	pub changes  : Vec<Change> ,
+
+	// Benchmarks that are in the run but not in the baseline.
+	//
+	pub new: Vec<BenchId>,
+}
+
+
+impl Comparison
+{
+	// Compare the benchmarks that have the same id in both sets. `threshold` is in percent.
+	//
+	pub fn new( baseline: &[Summary], current: &[Summary], threshold: f64 ) -> Self
+	{
+		let mut changes = Vec::new();
+		let mut new     = Vec::new();
+
+		for c in current
+		{
+			match baseline.iter().find( |b| b.id == c.id )
+			{
+				Some( b ) if b.stats.mean_ns > 0.0 => changes.push( Change
+				{
+					id         : c.id.clone()    ,
+					baseline_ns: b.stats.mean_ns ,
+					current_ns : c.stats.mean_ns ,
+				}),
+
+				_ => new.push( c.id.clone() ),
+			}
+		}
+
+		Self { threshold, changes, new }
+	}
+
+
+	// The benchmarks that got slower by more than the threshold.
+	//
+	pub fn regressions( &self ) -> impl Iterator<Item=&Change>
+	{
+		let threshold = self.threshold;
+
+		self.changes.iter().filter( move |c| c.percent() > threshold )
+	}
+
+
+	pub fn has_regressions( &self ) -> bool
+	{
+		self.regressions().next().is_some()
+	}
+}
+
+
+impl fmt::Display for Comparison
+{
+	fn fmt( &self, f: &mut fmt::Formatter<'_> ) -> fmt::Result
+	{
+		let ids: Vec<String> = self.changes.iter().map( |c| c.id.to_string() ).collect();
+		let width = ids.iter().map( String::len ).max().unwrap_or( 0 );
+
+		writeln!( f, "Comparison to baseline (threshold {}%):", self.threshold )?;
+		writeln!( f )?;
+
+		for (change, id) in self.changes.iter().zip( ids )
+		{
+			let percent = change.percent();
+
+			let verdict = match percent
+			{
+				p if p >  self.threshold => "REGRESSION",
+				p if p < -self.threshold => "improved"  ,
+				_                        => ""          ,
+			};
+
+			writeln!( f, "  {:<width$}  {:>+7.2}%  {}", id, percent, verdict, width = width )?;
+		}
+
+		for id in &self.new
+		{
+			writeln!( f, "  {}  not in baseline", id )?;
+		}
+
+		let regressions = self.regressions().count();
+
+		writeln!( f )?;
+		writeln!( f, "{} of {} benchmarks regressed.", regressions, self.changes.len() )
+	}
+}
+
+
+
+// Parse a threshold in percent. NaN, infinite and negative values would flag everything or
+// nothing as a regression, so they are rejected.
+//
+pub fn parse_threshold( threshold: &str ) -> Result<f64, String>
+{
+	let t = threshold.trim().parse::<f64>().map_err( |e| e.to_string() )?;
+
+	match t.is_finite() && t >= 0.0
+	{
+		true  => Ok( t ),
+		false => Err( "the threshold must be a finite number of percent, 0 or more".to_string() ),
+	}
+}
+
+
+// Save and compare to baselines of `bench` as configured by the environment. See the module docs.
+// Returns the exit code for the process: 1 if there are regressions, 2 if the baseline
+// can not be used, 0 otherwise.
+//
+pub fn baseline_from_env( bench: &str, records: &[Record] ) -> i32
+{
+	let summaries: Vec<Summary> = records.iter().map( Summary::new ).collect();
+
+	let mut code = 0;
+
+	if let Ok( name ) = env::var( BASELINE_VAR )
+	{
+		let threshold = match env::var( THRESHOLD_VAR )
+		{
+			Err( _ ) => DEFAULT_THRESHOLD,
+
+			Ok( t ) => match parse_threshold( &t )
+			{
+				Ok ( t ) => t,
+				Err( e ) => { eprintln!( "Invalid {}: {}: {}", THRESHOLD_VAR, t, e ); return 2 }
+			}
+		};
+
+		match load_baseline( bench, &name )
+		{
+			Ok( baseline ) =>
+			{
+				let comparison = Comparison::new( &baseline, &summaries, threshold );
+
+				println!( "\n{}", comparison );
+
+				if comparison.has_regressions() { code = 1; }
+			}
+
+			Err( e ) => { eprintln!( "Failed to load baseline {}: {}", name, e ); code = 2; }
+		}
+	}
+
+
+	// Save after comparing, so a baseline can be compared to and updated in the same run.
+	//
+	if let Ok( name ) = env::var( SAVE_BASELINE_VAR )
+	{
+		match save_baseline( bench, &name, &summaries )
+		{
+			Ok ( path ) => println!( "Baseline {} saved to {}", name, path.display() ),
+			Err( e    ) => { eprintln!( "Failed to save baseline {}: {}", name, e ); code = 2; }
+		}
+	}
+
+	code
+}
+
+
+
+#[ cfg( test ) ]
+//
+mod tests
+{
+	use super::*;
+	use std::time::Duration;
+
+	fn summary( executor: &str, us: u64 ) -> Summary
+	{
+		let id = BenchId::new( "Ring", executor, "BoundedRing" ).param( "nodes", 10 );
+
+		Summary::new( &Record::new( id, vec![ Duration::from_micros( us ) ] ) )
+	}
+
+
+	#[test]
+	//
+	fn compare()
+	{
+		let baseline = vec![ summary( "ThreadPool", 100 ), summary( "TokioTp", 100 ), summary( "AsyncStd", 100 ) ];
+		let current  = vec![ summary( "ThreadPool", 104 ), summary( "TokioTp", 110 ), summary( "AsyncStd", 80  ), summary( "SmolTp", 1 ) ];
+
+		let comparison = Comparison::new( &baseline, &current, 5.0 );
+
+		assert_eq!( 3, comparison.changes.len() );
+		assert_eq!( 1, comparison.new    .len() );
+
+		let regressions: Vec<String> = comparison.regressions().map( |c| c.id.executor.clone() ).collect();
+
+		assert_eq!( vec![ "TokioTp" ], regressions );
+		assert!( comparison.to_string().contains( "1 of 3 benchmarks regressed." ) );
+	}
+
+
+	#[test]
+	//
+	fn path_per_bench()
+	{
+		assert!( baseline_path( "ring"   , "v1" ).ends_with( "ring/v1.json"    ) );
+		assert!( baseline_path( "threads", "v1" ).ends_with( "threads/v1.json" ) );
+	}
+
+
+	#[test]
+	//
+	fn no_regressions()
+	{
+		let baseline = vec![ summary( "ThreadPool", 100 ) ];
+		let current  = vec![ summary( "ThreadPool", 50  ) ];
+
+		assert!( !Comparison::new( &baseline, &current, 5.0 ).has_regressions() );
+	}
+
+
+	#[test]
+	//
+	fn threshold()
+	{
+		assert_eq!( Ok( 5.0 ), parse_threshold( "5"   ) );
+		assert_eq!( Ok( 0.0 ), parse_threshold( "0"   ) );
+		assert_eq!( Ok( 2.5 ), parse_threshold( "2.5" ) );
+
+		assert!( parse_threshold( "NaN"  ).is_err() );
+		assert!( parse_threshold( "inf"  ).is_err() );
+		assert!( parse_threshold( "-1"   ).is_err() );
+		assert!( parse_threshold( "five" ).is_err() );
+	}
+}
@@ -0,0 +1,90 @@
+//! Save a results file as a baseline or compare one to a baseline, without running the benchmarks.
+//! Exits with 1 if there are regressions, so it can be used in CI.
+//!
+use executor_benchmarks::*;
+use std::{ env, process };
+
+
+const USAGE: &str = "\
+Usage: baseline save    <name> [results.json] [--bench <bench>]
+       baseline compare <name> [results.json] [--bench <bench>] [--threshold <percent>]
+
+The bench defaults to ring, and it's results are read from <results dir>/<bench>-latest.json by
+default. The threshold defaults to 5 percent. Baselines are stored in <baseline dir>/<bench>.";
+
+
+fn main()
+{
+	let mut positional = Vec::new();
+	let mut threshold  = DEFAULT_THRESHOLD;
+	let mut bench      = "ring".to_string();
+	let mut args       = env::args().skip( 1 );
+
+	while let Some( arg ) = args.next()
+	{
+		match arg.as_str()
+		{
+			"--help" | "-h" =>
+			{
+				println!( "{}", USAGE );
+				return;
+			}
+
+			"--threshold" => match args.next().map( |t| parse_threshold( &t ) )
+			{
+				Some( Ok ( t ) ) => threshold = t,
+				Some( Err( e ) ) => fail( &format!( "invalid --threshold: {}", e ) ),
+				None             => fail( "--threshold needs a number" ),
+			}
+
+			"--bench" => match args.next()
+			{
+				Some( b ) => bench = b,
+				None      => fail( "--bench needs the name of a bench" ),
+			}
+
+			_ if arg.starts_with( "--" ) => fail( &format!( "unknown option: {}", arg ) ),
+
+			_ => positional.push( arg ),
+		}
+	}
+
+	if positional.len() < 2 || positional.len() > 3
+	{
+		fail( "expected a command and a baseline name" );
+	}
+
+	let path = positional.get( 2 ).map( Into::into ).unwrap_or_else( || results_dir().join( format!( "{}-latest.json", bench ) ) );
+
+	let current = load_results( &path ).unwrap_or_else( |e| fail( &e ) );
+	let name    = &positional[1];
+
+	match positional[0].as_str()
+	{
+		"save" =>
+		{
+			let path = save_baseline( &bench, name, &current ).unwrap_or_else( |e| fail( &e ) );
+
+			println!( "Baseline {} saved to {}", name, path.display() );
+		}
+
+		"compare" =>
+		{
+			let baseline   = load_baseline( &bench, name ).unwrap_or_else( |e| fail( &e ) );
+			let comparison = Comparison::new( &baseline, &current, threshold );
+
+			print!( "{}", comparison );
+
+			if comparison.has_regressions() { process::exit( 1 ); }
+		}
+
+		other => fail( &format!( "unknown command: {}", other ) ),
+	}
+}
+
+
+fn fail( msg: &str ) -> !
+{
+	eprintln!( "error: {}\n\n{}", msg, USAGE );
+	process::exit( 2 );
+}
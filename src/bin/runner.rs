@@ -0,0 +1,291 @@
+//! Run a single workload on a single executor and print the timings, without the sampling and
+//! analysis of criterion. Useful to quickly try one scenario, eg:
+//!
+//! `cargo run --release --bin runner -- TokioTp HandleRing --nodes 1000`
+//!
+//! Run with `--help` for all options and `--list` for the supported combinations.
+//!
+use executor_benchmarks::*;
+use std::{ env, process, str::FromStr, time::Duration };
+
+
+const USAGE: &str = "\
+Usage: runner <executor> <workload> [options]
+       runner --scenario <file> [--warmup <n>]
+       runner --list
+
+Options:
+  --nodes    <n>     number of nodes in the ring (default 100)
+  --capacity <n>     capacity of the channels between nodes (default 1)
+  --laps     <n>     how many times each message goes round the ring (default 1)
+  --forward  <mode>  inline, detach or await (default detach)
+  --threads  <n>     worker threads of multithreaded executors (default: the executor's default)
+  --iters    <n>     number of timed iterations (default 10)
+  --warmup   <n>     number of iterations to run before timing (default 1)
+  --scenario <file>  run every entry of a scenario file, with it's sample size as the number of iterations
+  --verify           check that every message arrives in order and completes it's laps, and that no task
+                     outlives the run (slower, don't compare the timings)
+
+Worker threads are pinned if BENCH_PIN_CORES is set, eg. BENCH_PIN_CORES=0,2-5.
+An iteration that takes more than BENCH_TIMEOUT seconds (default 60, 0 disables) aborts the run.";
+
+
+struct Args
+{
+	entries: Vec<Entry> ,
+	warmup : usize      ,
+}
+
+
+fn main()
+{
+	let args = match parse( env::args().skip( 1 ).collect() )
+	{
+		Ok ( Some( args ) ) => args,
+		Ok ( None         ) => return,
+
+		Err( e ) =>
+		{
+			eprintln!( "error: {}\n\n{}", e, USAGE );
+			process::exit( 2 );
+		}
+	};
+
+	let pinning = Pinning::from_env();
+	pinning.pin_driver();
+
+	for entry in args.entries
+	{
+		run( entry, args.warmup, &pinning );
+	}
+
+	export_results( "runner" );
+
+	process::exit( baseline_from_env( "runner", &results() ) );
+}
+
+
+fn run( entry: Entry, warmup: usize, pinning: &Pinning )
+{
+	let Entry { id, executor, workload, config, threads, samples } = entry;
+
+	if !threads_fit( &executor, threads )
+	{
+		eprintln!( "Skipping {}: the async-std runtime already runs {} threads.", id, async_std_threads() );
+		return;
+	}
+
+	let mut routine = match routine( &executor, &workload, config, threads, pinning )
+	{
+		Some( routine ) => routine,
+
+		None =>
+		{
+			eprintln!( "error: {} can not run {}, see --list.", executor, workload );
+			process::exit( 2 );
+		}
+	};
+
+	println!( "{} {}: {}", executor, workload, config );
+
+	// The warm up is watched too, it's the first run to hang if the workload is broken.
+	//
+	let watch = Watch::new( &id );
+
+	for _ in 0..warmup
+	{
+		if let Some( watch ) = &watch { watch.iteration(); }
+
+		routine();
+	}
+
+	drop( watch );
+
+	for _ in 0..samples
+	{
+		timed( &id, 1, &mut routine );
+	}
+
+	let record = results().into_iter().find( |r| r.id == id ).expect( "find record" );
+	let stats  = record.stats();
+	let ns     = |ns: f64| Duration::from_nanos( ns as u64 );
+
+	println!
+	(
+		"{} iterations: mean {:?}, median {:?}, stddev {:?}, min {:?}, max {:?}",
+		samples, ns( stats.mean_ns ), ns( stats.median_ns ), ns( stats.stddev_ns ), ns( stats.min_ns ), ns( stats.max_ns ),
+	);
+
+	// Every node's message gets forwarded by every node on every lap.
+	//
+	let hops = ( config.nodes * config.end() ) as f64;
+
+	if stats.mean_ns > 0.0 && workload.ends_with( "Ring" )
+	{
+		println!( "{:.0} messages forwarded per second", hops / stats.mean_ns * 1e9 );
+	}
+
+	if let Some( allocs ) = record.allocations
+	{
+		let a = AllocStats::new( allocs, record.iterations );
+
+		match workload.ends_with( "Ring" )
+		{
+			true  => println!( "{:.1} allocations ({:.0} bytes) per iteration, {:.2} per hop", a.allocs_per_iter, a.bytes_per_iter, a.allocs_per_iter / hops ),
+			false => println!( "{:.1} allocations ({:.0} bytes) per iteration", a.allocs_per_iter, a.bytes_per_iter ),
+		}
+	}
+
+	if let Some( u ) = UsageStats::from_record( &record )
+	{
+		let ns = |ns: f64| Duration::from_nanos( ns as u64 );
+
+		println!
+		(
+			"cpu per iteration: user {:?}, sys {:?}, {:.2} cores busy, {:.1} voluntary and {:.1} involuntary context switches, {:.1} minor and {:.1} major page faults",
+			ns( u.user_ns ), ns( u.sys_ns ), u.cpu_ratio, u.voluntary_switches, u.involuntary_switches, u.minor_faults, u.major_faults,
+		);
+	}
+
+	if let Some( p ) = PerfStats::from_record( &record )
+	{
+		println!
+		(
+			"perf per iteration: task clock {:?}, {:.2} cpus utilized, {:.1} context switches, {:.1} cpu migrations",
+			Duration::from_nanos( p.task_clock_ns as u64 ), p.cpus_utilized, p.context_switches, p.cpu_migrations,
+		);
+	}
+
+	if let Some( histogram ) = &record.latency
+	{
+		let l  = Latency::new( histogram );
+		let ns = |ns: u64| Duration::from_nanos( ns );
+
+		println!
+		(
+			"{} latencies: p50 {:?}, p90 {:?}, p99 {:?}, p99.9 {:?}, max {:?}",
+			l.count, ns( l.p50_ns ), ns( l.p90_ns ), ns( l.p99_ns ), ns( l.p999_ns ), ns( l.max_ns ),
+		);
+	}
+
+	if let Some( b ) = &record.balance
+	{
+		println!( "tasks per thread, busiest first: {:?}, imbalance {:.2}", b.tasks_per_thread, b.imbalance() );
+	}
+
+	if let Some( m ) = &record.memory
+	{
+		match m.heap_per_task()
+		{
+			Some( heap ) => println!( "{} idle tasks: {:.0} bytes rss, {:.0} bytes heap per task, peak rss {} bytes", m.tasks, m.rss_per_task(), heap, m.peak_rss ),
+			None         => println!( "{} idle tasks: {:.0} bytes rss per task, peak rss {} bytes"                  , m.tasks, m.rss_per_task()      , m.peak_rss ),
+		}
+	}
+}
+
+
+// Returns `None` if there is nothing to run, eg. for `--help` and `--list`.
+//
+fn parse( args: Vec<String> ) -> Result< Option<Args>, String >
+{
+	let mut positional = Vec::new();
+	let mut config     = RingConfig::new( 100, Forward::Detach );
+	let mut threads    = None;
+	let mut iters      = 10;
+	let mut warmup     = 1;
+	let mut scenario   = None;
+	let mut verify     = false;
+
+	let mut args = args.into_iter();
+
+	while let Some( arg ) = args.next()
+	{
+		match arg.as_str()
+		{
+			"--help" | "-h" =>
+			{
+				println!( "{}", USAGE );
+				return Ok( None );
+			}
+
+			"--list" =>
+			{
+				for (executor, workload) in ROUTINES.iter().chain( LATENCY_ROUTINES ).chain( MEMORY_ROUTINES ).chain( BALANCE_ROUTINES )
+				{
+					println!( "{} {}", executor, workload );
+				}
+
+				return Ok( None );
+			}
+
+			"--nodes"    => config.nodes    = value( &arg, args.next() )?,
+			"--capacity" => config.capacity = value( &arg, args.next() )?,
+			"--laps"     => config.laps     = value( &arg, args.next() )?,
+			"--forward"  => config.forward  = value( &arg, args.next() )?,
+			"--threads"  => threads         = Some( value( &arg, args.next() )? ),
+			"--iters"    => iters           = value( &arg, args.next() )?,
+			"--warmup"   => warmup          = value( &arg, args.next() )?,
+			"--scenario" => scenario        = Some( value::<String>( &arg, args.next() )? ),
+			"--verify"   => verify          = true,
+
+			_ if arg.starts_with( "--" ) => return Err( format!( "unknown option: {}", arg ) ),
+
+			_ => positional.push( arg ),
+		}
+	}
+
+
+	if let Some( path ) = scenario
+	{
+		if !positional.is_empty()
+		{
+			return Err( "a scenario can not be combined with an executor and a workload".to_string() );
+		}
+
+		let mut entries = Scenario::load( path )?.entries();
+
+		for entry in &mut entries { entry.config.verify |= verify; }
+
+		return Ok( Some( Args { entries, warmup } ) );
+	}
+
+
+	if positional.len() != 2
+	{
+		return Err( "expected an executor and a workload".to_string() );
+	}
+
+	// See `RingConfig::capacity` for why it can't be 0.
+	//
+	if config.nodes    < 2 { return Err( "a ring needs at least 2 nodes".to_string() ) }
+	if config.laps     < 1 { return Err( "laps must be at least 1"      .to_string() ) }
+	if config.capacity < 1 { return Err( "capacity must be at least 1"  .to_string() ) }
+	if iters           < 1 { return Err( "iters must be at least 1"     .to_string() ) }
+
+	config.verify = verify;
+
+	let workload = positional.pop().expect( "two positional arguments" );
+	let executor = positional.pop().expect( "two positional arguments" );
+
+	let id = BenchId::new( "Runner", executor.as_str(), workload.as_str() )
+
+		.param( "forward" , config.forward  )
+		.param( "nodes"   , config.nodes    )
+		.param( "capacity", config.capacity )
+		.param( "laps"    , config.laps     )
+	;
+
+	let entry = Entry { id, executor, workload, config, threads, samples: iters };
+
+	Ok( Some( Args { entries: vec![ entry ], warmup } ) )
+}
+
+
+fn value<T>( option: &str, value: Option<String> ) -> Result<T, String>
+
+	where T: FromStr, T::Err: ToString,
+{
+	let value = value.ok_or_else( || format!( "missing value for {}", option ) )?;
+
+	value.parse().map_err( |e: T::Err| format!( "invalid value for {}: {}: {}", option, value, e.to_string() ) )
+}
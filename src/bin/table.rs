@@ -0,0 +1,59 @@
+//! Print the results of a run as Markdown comparison tables, eg:
+//!
+//! `cargo run --bin table -- target/results/ring-latest.json --column nodes`
+//!
+use executor_benchmarks::*;
+use std::{ env, process };
+
+
+const USAGE: &str = "\
+Usage: table [results.json] [--column <param>]
+
+Print a Markdown table per group and set of parameters, with a row per routine and a column
+per value of the column parameter. Reads <results dir>/ring-latest.json by default.
+
+Options:
+  --column <param>  the parameter to use for the columns (default nodes)";
+
+
+fn main()
+{
+	let mut path   = results_dir().join( "ring-latest.json" );
+	let mut column = "nodes".to_string();
+	let mut args   = env::args().skip( 1 );
+
+	while let Some( arg ) = args.next()
+	{
+		match arg.as_str()
+		{
+			"--help" | "-h" =>
+			{
+				println!( "{}", USAGE );
+				return;
+			}
+
+			"--column" => match args.next()
+			{
+				Some( c ) => column = c,
+				None      => fail( "missing value for --column" ),
+			}
+
+			_ if arg.starts_with( "--" ) => fail( &format!( "unknown option: {}", arg ) ),
+
+			_ => path = arg.into(),
+		}
+	}
+
+	match load_results( &path )
+	{
+		Ok ( summaries ) => print!( "{}", markdown( &summaries, &column ) ),
+		Err( e         ) => fail( &e ),
+	}
+}
+
+
+fn fail( msg: &str ) -> !
+{
+	eprintln!( "error: {}\n\n{}", msg, USAGE );
+	process::exit( 2 );
+}
@@ -1,121 +1,37 @@
 //! This uses bounded channels and has the possibility to spawn each message forwarding on the executor
-//! rather than awaiting.
+//! rather than awaiting. See [`Forward`](crate::Forward).
 //!
-use futures::{ SinkExt, StreamExt, channel::mpsc, task::{ LocalSpawn, LocalSpawnExt, Spawn, SpawnExt } };
-use std::{ sync::{ atomic::{ AtomicUsize, Ordering } } };
-use log::*;
+use futures::{ channel::mpsc, future::RemoteHandle, task::{ LocalSpawn, LocalSpawnExt, Spawn, SpawnExt } };
+use std::future::Future;
+use crate::{ Forward, Message, Node, Ring, RingConfig, RingSpawn };
 
-static DONE: AtomicUsize = AtomicUsize::new( 0 );
 
-
-pub struct BoundedRing
-{
-	nodes: Option< Vec<BoundedNode> > ,
-}
+pub struct BoundedRing( Ring );
 
 
 impl BoundedRing
 {
 	// Create channels between all the nodes.
 	//
-	pub fn new( n: usize ) -> Self
+	pub fn new( n: usize, forward: Forward ) -> Self
 	{
-		assert!( n > 1 );
-
-		let mut nodes = Vec::with_capacity( n );
-
-		// The channel always has one slot per sender, so this is a bounded
-		// channel of size channel_size + 1. I choose a low number in the hopes
-		// that some tasks will block, creating a bit more work for the executor
-		// and a more realistic benchmark.
-		//
-		let channel_size = 1;
-
-		// The connection between the last and the first node.
-		//
-		let (last_tx, last_rx) = mpsc::channel( channel_size );
-
-		// The first node
-		//
-		let (tx, mut next_rx) = mpsc::channel( channel_size );
-
-		nodes.push( BoundedNode { id: 1, n, tx, rx: last_rx } );
-
-
-		// All but first and last.
-		// Note that 1..1 does not do anything, so it works if n==2.
-		//
-		for id in 2..n
-		{
-			let (tx, rx) = mpsc::channel( channel_size );
-
-			nodes.push( BoundedNode { id, n, tx, rx: next_rx } );
-
-			next_rx = rx;
-		}
-
-
-		// The last node
-		//
-		nodes.push( BoundedNode { id: n, n, tx: last_tx, rx: next_rx } );
-
-		Self
-		{
-			nodes: Some( nodes ),
-		}
+		Self::with_config( RingConfig::new( n, forward ) )
 	}
 
 
-
-	// // Return a vec of futures that await the operation of each node.
-	// // You can either spawn them, join them, use futures unordered, ... to compare performance.
-	// //
-	// pub fn start_parallel( &mut self ) -> Vec<impl Future<Output = ()> >
-	// {
-	// 	self.nodes.take().unwrap().into_iter().map( move |mut node|
-	// 	{
-	// 		async move
-	// 		{
-	// 			node.run().await;
-	// 		}
-
-	// 	}).collect()
-	// }
-
+	// Create a ring with a custom channel capacity and number of laps.
+	//
+	pub fn with_config( config: RingConfig ) -> Self
+	{
+		Self( Ring::with_config( config ) )
+	}
 
 
 	// Run the benchmark.
 	//
-	pub async fn run( &mut self, exec: impl Spawn + Clone + Send + 'static )
+	pub async fn run( &mut self, exec: impl Spawn + Clone + Send + Sync + 'static )
 	{
-		debug!( "BoundedRing: start" );
-
-		// Need to reset the DONE counter, since it might be reused on repeated benchmarks.
-		//
-		DONE.store( 0, Ordering::SeqCst );
-
-
-		let (done_tx, mut done_rx) = mpsc::channel(0);
-
-
-		for mut node in self.nodes.take().unwrap().into_iter()
-		{
-			let done_tx = done_tx.clone();
-
-			let exec2 = exec.clone();
-			exec.spawn( async move { node.run( done_tx, exec2 ).await; } ).expect( "spawn node" );
-		};
-
-		let res = done_rx.next().await;
-
-		debug_assert!( res.is_some() );
-
-		debug!( "BoundedRing: end" );
-
-
-		// Just a memory barrier.
-		//
-		// DONE.store( 0, Ordering::SeqCst );
+		self.0.run( WithSpawn( exec ) ).await;
 	}
 
 
@@ -124,129 +40,70 @@ impl BoundedRing
 	//
 	pub async fn run_local( &mut self, exec: impl LocalSpawn + Spawn + Clone + 'static )
 	{
-		debug!( "BoundedRing: start" );
-
-		// Need to reset the DONE counter, since it might be reused on repeated benchmarks.
-		//
-		DONE.store( 0, Ordering::SeqCst );
-
-
-		let (done_tx, mut done_rx) = mpsc::channel(0);
-
-
-		for mut node in self.nodes.take().unwrap().into_iter()
-		{
-			let done_tx = done_tx.clone();
-
-			let exec2 = exec.clone();
-			exec.spawn_local( async move { node.run( done_tx, exec2 ).await; } ).expect( "spawn node" );
-		};
-
-		let res = done_rx.next().await;
-
-		debug_assert!( res.is_some() );
-		drop( exec );
-
-		debug!( "BoundedRing: end" );
-
-
-		// Just a memory barrier.
-		//
-		// DONE.store( 0, Ordering::SeqCst );
+		self.0.run( WithLocalNodes( exec ) ).await;
 	}
 }
 
 
-// Each node will start by sending a 1 to the next node. When the counter has come back and
-// been incremented by all nodes, the operation is complete.
-//
-pub struct BoundedNode
-{
-	id: usize                 ,
-	n : usize                 ,
-	tx: mpsc::Sender  <usize> ,
-	rx: mpsc::Receiver<usize> ,
-}
 
-impl BoundedNode
-{
-	async fn run( &mut self, mut done_tx: mpsc::Sender<()>, exec: impl Spawn )
-	{
-		debug!( "BoundedNode {}: run", self.id );
-
-		let mut tx = self.tx.clone();
-		exec.spawn( async move { tx.send( 1 ).await.expect( "Node: send initial message" ); } )
-
-			.expect( "spawn forward" );
-
-
-		// Two ways out of this loop:
-		// - if we are the last node to finish, we break and then close our channel.
-		// - if we aren't the last, we will end the loop when the channel is closed and returns None.
-		//
-		while let Some(msg) = self.rx.next().await
-		{
-			debug_assert!( !msg > self.n );
+// Spawns everything with `Spawn`.
+//
+#[ derive( Clone ) ]
+//
+pub(crate) struct WithSpawn<S>( pub(crate) S );
 
-			// When our message comes back, it should be counted by everyone, so it should be n.
-			// if the msg < n, it didn't originate here.
-			//
-			if msg == self.n
-			{
-				trace!( "BoundedNode {}: received our own message back", self.id );
 
-				// Store the fact that we are done.
-				//
-				let old = DONE.fetch_add( 1, Ordering::SeqCst );
+impl<S> RingSpawn for WithSpawn<S>
 
-				// If we are the last one.
-				//
-				if old+1 == self.n
-				{
-					trace!( "BoundedNode {}: all done", self.id );
+	where S: Spawn + Clone + Send + Sync + 'static
+{
+	type Handle = RemoteHandle<()>;
 
-					break;
-				}
+	fn spawn_node<M: Message>( &self, node: Node<M>, done: mpsc::Sender<()> )
+	{
+		self.0.spawn( node.run( self.clone(), done ) ).expect( "spawn node" );
+	}
 
-				// else continue passing on messages for the other nodes.
-				//
-				continue;
-			}
+	fn detach( &self, task: impl Future<Output = ()> + Send + 'static )
+	{
+		self.0.spawn( task ).expect( "spawn forward" );
+	}
 
-			trace!( "BoundedNode {}: forwarding a message", self.id );
+	fn spawn_awaited( &self, task: impl Future<Output = ()> + Send + 'static ) -> Self::Handle
+	{
+		self.0.spawn_with_handle( task ).expect( "spawn forward" )
+	}
+}
 
-			// forward the message
-			//
-			let mut tx = self.tx.clone();
 
-			exec.spawn( async move { tx.send( msg + 1 ).await.expect( "BoundedNode: forward message" ); } )
 
-				.expect( "spawn forward" );
-		}
+// Spawns the nodes with `LocalSpawn`, since they hold the executor, which doesn't have to be `Send`.
+// Messages are forwarded with `Spawn`.
+//
+#[ derive( Clone ) ]
+//
+pub(crate) struct WithLocalNodes<S>( pub(crate) S );
 
-		trace!( "BoundedNode {}: close our sender", self.id );
 
-		// Allow nodes that where still passing on messages to detect that we are done.
-		//
-		self.tx.close().await.expect( "close channel" );
+impl<S> RingSpawn for WithLocalNodes<S>
 
-		drop( exec );
+	where S: LocalSpawn + Spawn + Clone + 'static
+{
+	type Handle = RemoteHandle<()>;
 
-		// Now count down to make sure everyone has dropped the executor before signaling done_tx.
-		// This is important because it's a benchmark and we don't want code to keep running after
-		// each iteration and also because tokio runtime panics if it get's dropped from async context,
-		// so we absolutely need to make sure that these are dropped before the block_on returns.
-		//
-		let old = DONE.fetch_sub( 1, Ordering::SeqCst );
+	fn spawn_node<M: Message>( &self, node: Node<M>, done: mpsc::Sender<()> )
+	{
+		self.0.spawn_local( node.run( self.clone(), done ) ).expect( "spawn node" );
+	}
 
-		// We are the last one to drop our exec.
-		//
-		if old - 1 == 0
-		{
-			done_tx.send(()).await.expect( "Send DONE" );
-		}
+	fn detach( &self, task: impl Future<Output = ()> + Send + 'static )
+	{
+		self.0.spawn( task ).expect( "spawn forward" );
+	}
 
-		debug!( "BoundedNode {}: run END", self.id );
+	fn spawn_awaited( &self, task: impl Future<Output = ()> + Send + 'static ) -> Self::Handle
+	{
+		self.0.spawn_with_handle( task ).expect( "spawn forward" )
 	}
 }
 
@@ -259,15 +116,21 @@ mod tests
 	#[ allow( unused_imports ) ] // false positive
 	//
 	use super::*;
+	use futures::executor::{ block_on, ThreadPool };
 
+	// Every forwarding mode should terminate on a multithreaded executor.
+	//
 	#[test]
 	//
-	fn off_by_one_or_not()
+	fn all_forward_modes()
 	{
-		let ring2 = BoundedRing::new( 2 );
-		assert_eq!( 2, ring2.nodes.unwrap().len() );
+		let pool = ThreadPool::new().expect( "create threadpool" );
 
-		let ring2 = BoundedRing::new( 3 );
-		assert_eq!( 3, ring2.nodes.unwrap().len() );
+		for forward in Forward::ALL.iter()
+		{
+			let mut ring = BoundedRing::new( 10, *forward );
+
+			block_on( ring.run( pool.clone() ) );
+		}
 	}
 }
@@ -0,0 +1,222 @@
+//! The different ways of driving a futures `LocalPool`. Applications that integrate the pool
+//! in another event loop (eg. a GUI) often can't use `run_until`. As a reference, `BlockOn` runs
+//! the same tasks with nothing but `futures::executor::block_on`.
+//!
+use futures::{ executor::{ LocalPool, LocalSpawner, block_on }, future::{ Future, LocalFutureObj, poll_fn }, pin_mut, stream::{ FuturesUnordered, StreamExt }, task::{ LocalSpawn, LocalSpawnExt, SpawnError } };
+use std::{ cell::{ Cell, RefCell }, fmt, rc::Rc, task::Poll };
+
+
+#[ derive( Copy, Clone, Debug, PartialEq, Eq, Hash ) ]
+//
+pub enum Drive
+{
+	// `LocalPool::run_until` the future.
+	//
+	RunUntil,
+
+	// Spawn the future and `LocalPool::run` until all tasks are complete.
+	//
+	Run,
+
+	// Spawn the future and call `LocalPool::run_until_stalled` in a loop until it's complete.
+	//
+	RunUntilStalled,
+
+	// Spawn the future and call `LocalPool::try_run_one` in a loop until it's complete.
+	//
+	TryRunOne,
+
+	// `futures::executor::block_on` the future, which polls the tasks it spawns itself. This doesn't
+	// use the pool.
+	//
+	BlockOn,
+}
+
+
+impl Drive
+{
+	pub const ALL: [Drive; 5] = [ Drive::RunUntil, Drive::Run, Drive::RunUntilStalled, Drive::TryRunOne, Drive::BlockOn ];
+
+
+	// Drive the pool in this mode until the future `task` returns is complete. `task` gets the
+	// spawner for this mode.
+	//
+	pub fn run<Fut>( self, pool: &mut LocalPool, task: impl FnOnce( DriveSpawner ) -> Fut )
+
+		where Fut: Future<Output = ()> + 'static
+	{
+		let spawner = DriveSpawner::Pool( pool.spawner() );
+
+		match self
+		{
+			Drive::RunUntil => pool.run_until( task( spawner ) ),
+
+			Drive::Run =>
+			{
+				pool.spawner().spawn_local( task( spawner ) ).expect( "spawn future" );
+				pool.run();
+			}
+
+			Drive::RunUntilStalled =>
+			{
+				let done = spawn_flagged( pool, task( spawner ) );
+
+				while !done.get() { pool.run_until_stalled(); }
+			}
+
+			Drive::TryRunOne =>
+			{
+				let done = spawn_flagged( pool, task( spawner ) );
+
+				while !done.get() { pool.try_run_one(); }
+			}
+
+			Drive::BlockOn =>
+			{
+				let tasks = BlockOnTasks::default();
+
+				block_on( tasks.clone().run_until( task( DriveSpawner::BlockOn( tasks ) ) ) );
+			}
+		}
+	}
+}
+
+
+impl fmt::Display for Drive
+{
+	fn fmt( &self, f: &mut fmt::Formatter<'_> ) -> fmt::Result
+	{
+		match self
+		{
+			Drive::RunUntil        => write!( f, "run_until"         ),
+			Drive::Run             => write!( f, "run"               ),
+			Drive::RunUntilStalled => write!( f, "run_until_stalled" ),
+			Drive::TryRunOne       => write!( f, "try_run_one"       ),
+			Drive::BlockOn         => write!( f, "block_on"          ),
+		}
+	}
+}
+
+
+
+// Spawns tasks for the mode a future is driven in.
+//
+#[ derive( Clone ) ]
+//
+pub enum DriveSpawner
+{
+	Pool   ( LocalSpawner ),
+	BlockOn( BlockOnTasks ),
+}
+
+
+impl LocalSpawn for DriveSpawner
+{
+	fn spawn_local_obj( &self, future: LocalFutureObj<'static, ()> ) -> Result<(), SpawnError>
+	{
+		match self
+		{
+			DriveSpawner::Pool   ( spawner ) => spawner.spawn_local_obj( future ),
+			DriveSpawner::BlockOn( tasks   ) => tasks  .spawn_local_obj( future ),
+		}
+	}
+}
+
+
+
+// The tasks of a future driven by `Drive::BlockOn`. Spawned tasks wait in `incoming` until the
+// future that runs them takes them, so spawning doesn't borrow the tasks while they are polled.
+//
+#[ derive( Clone, Default ) ]
+//
+pub struct BlockOnTasks
+{
+	incoming: Rc< RefCell< Vec< LocalFutureObj<'static, ()> > > >,
+}
+
+
+impl BlockOnTasks
+{
+	// Poll `future` and the tasks spawned here until `future` is complete. Tasks that are still
+	// running then are dropped.
+	//
+	async fn run_until( self, future: impl Future<Output = ()> )
+	{
+		let mut running = FuturesUnordered::new();
+
+		pin_mut!( future );
+
+		poll_fn( |cx| loop
+		{
+			running.extend( self.incoming.borrow_mut().drain( .. ) );
+
+			// Tasks that get woken later wake the waker of `cx` through `running`.
+			//
+			while let Poll::Ready( Some(()) ) = running.poll_next_unpin( cx ) {}
+
+			if future.as_mut().poll( cx ).is_ready() { return Poll::Ready(()) }
+
+			// Only the future and the tasks can spawn, so if they didn't, there is nothing to do
+			// until something is woken.
+			//
+			if self.incoming.borrow().is_empty() { return Poll::Pending }
+
+		}).await
+	}
+}
+
+
+impl LocalSpawn for BlockOnTasks
+{
+	fn spawn_local_obj( &self, future: LocalFutureObj<'static, ()> ) -> Result<(), SpawnError>
+	{
+		self.incoming.borrow_mut().push( future );
+
+		Ok(())
+	}
+}
+
+
+// Spawn the future with a flag that gets set when it completes.
+//
+fn spawn_flagged( pool: &LocalPool, future: impl Future<Output = ()> + 'static ) -> Rc< Cell<bool> >
+{
+	let done  = Rc::new( Cell::new( false ) );
+	let done2 = done.clone();
+
+	pool.spawner().spawn_local( async move
+	{
+		future.await;
+		done2.set( true );
+
+	}).expect( "spawn future" );
+
+	done
+}
+
+
+
+#[ cfg( test ) ]
+//
+mod tests
+{
+	use super::*;
+	use crate::{ LocalRing, Forward };
+
+	#[test]
+	//
+	fn all_drive_modes()
+	{
+		let mut pool = LocalPool::new();
+
+		for drive in Drive::ALL.iter()
+		{
+			for forward in Forward::ALL.iter()
+			{
+				let mut ring = LocalRing::new( 10, *forward );
+
+				drive.run( &mut pool, move |spawner| async move { ring.run( spawner ).await; } );
+			}
+		}
+	}
+}
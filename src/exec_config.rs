@@ -0,0 +1,203 @@
+//! Configurations for the builders of the multithreaded executors, so benchmarks can run on a
+//! matrix of configurations rather than only on the defaults.
+//!
+use std::{ fmt, io };
+use tokio::runtime::Builder;
+use futures::executor::{ ThreadPool, ThreadPoolBuilder };
+
+
+// A smaller stack than the default of both tokio and ThreadPool (2MiB), to see whether it
+// makes any difference.
+//
+const SMALL_STACK: usize = 256 * 1024;
+
+
+// Options for the tokio threaded scheduler. `None` means the tokio default.
+//
+#[ derive( Copy, Clone, Debug, Default, PartialEq, Eq ) ]
+//
+pub struct TokioConfig
+{
+	pub core_threads: Option<usize> ,
+	pub max_threads : Option<usize> ,
+	pub stack_size  : Option<usize> ,
+	pub enable_all  : bool          ,
+}
+
+
+impl TokioConfig
+{
+	// Create a builder for the threaded scheduler with this configuration. It can be
+	// built directly for a native runtime or passed to `TokioTp::try_from`.
+	//
+	pub fn builder( &self ) -> Builder
+	{
+		let mut builder = Builder::new();
+
+		builder.threaded_scheduler();
+
+		if let Some( n    ) = self.core_threads { builder.core_threads     ( n    ); }
+		if let Some( n    ) = self.max_threads  { builder.max_threads      ( n    ); }
+		if let Some( size ) = self.stack_size   { builder.thread_stack_size( size ); }
+
+		if self.enable_all { builder.enable_all(); }
+
+		builder
+	}
+
+
+	// All combinations of the options we benchmark.
+	//
+	pub fn matrix() -> Vec<Self>
+	{
+		let     cores  = num_cpus::get();
+		let mut matrix = Vec::new();
+
+		for core_threads in [ None, Some( 1 ), Some( cores ) ].iter()
+		{
+			for max_threads in [ None, Some( cores ) ].iter()
+			{
+				for stack_size in [ None, Some( SMALL_STACK ) ].iter()
+				{
+					for enable_all in [ false, true ].iter()
+					{
+						matrix.push( Self
+						{
+							core_threads: *core_threads ,
+							max_threads : *max_threads  ,
+							stack_size  : *stack_size   ,
+							enable_all  : *enable_all   ,
+						});
+					}
+				}
+			}
+		}
+
+		matrix
+	}
+}
+
+
+impl fmt::Display for TokioConfig
+{
+	fn fmt( &self, f: &mut fmt::Formatter<'_> ) -> fmt::Result
+	{
+		write!( f, "core {}, max {}, stack {}, {}",
+
+			opt( self.core_threads ) ,
+			opt( self.max_threads  ) ,
+			opt( self.stack_size   ) ,
+			if self.enable_all { "enable_all" } else { "no drivers" } ,
+		)
+	}
+}
+
+
+
+// Options for the futures ThreadPool. `None` means the futures default.
+//
+#[ derive( Copy, Clone, Debug, Default, PartialEq, Eq ) ]
+//
+pub struct PoolConfig
+{
+	pub pool_size : Option<usize> ,
+	pub stack_size: Option<usize> ,
+}
+
+
+impl PoolConfig
+{
+	// Create a builder with this configuration, so further options (like pinning) can be added.
+	//
+	pub fn builder( &self ) -> ThreadPoolBuilder
+	{
+		let mut builder = ThreadPool::builder();
+
+		if let Some( n    ) = self.pool_size  { builder.pool_size ( n    ); }
+		if let Some( size ) = self.stack_size { builder.stack_size( size ); }
+
+		builder
+	}
+
+
+	// Create a threadpool with this configuration.
+	//
+	pub fn create( &self ) -> Result<ThreadPool, io::Error>
+	{
+		self.builder().create()
+	}
+
+
+	// All combinations of the options we benchmark.
+	//
+	pub fn matrix() -> Vec<Self>
+	{
+		let     cores  = num_cpus::get();
+		let mut matrix = Vec::new();
+
+		for pool_size in [ None, Some( 1 ), Some( cores ) ].iter()
+		{
+			for stack_size in [ None, Some( SMALL_STACK ) ].iter()
+			{
+				matrix.push( Self { pool_size: *pool_size, stack_size: *stack_size } );
+			}
+		}
+
+		matrix
+	}
+}
+
+
+impl fmt::Display for PoolConfig
+{
+	fn fmt( &self, f: &mut fmt::Formatter<'_> ) -> fmt::Result
+	{
+		write!( f, "size {}, stack {}", opt( self.pool_size ), opt( self.stack_size ) )
+	}
+}
+
+
+fn opt( value: Option<usize> ) -> String
+{
+	value.map( |v| v.to_string() ).unwrap_or_else( || "default".to_string() )
+}
+
+
+
+#[ cfg( test ) ]
+//
+mod tests
+{
+	use super::*;
+
+	// Make sure we don't generate invalid combinations, like max_threads < core_threads.
+	//
+	#[test]
+	//
+	fn tokio_matrix_builds()
+	{
+		let matrix = TokioConfig::matrix();
+
+		assert!( matrix.contains( &TokioConfig::default() ) );
+
+		for config in matrix
+		{
+			config.builder().build().expect( &config.to_string() );
+		}
+	}
+
+
+	#[test]
+	//
+	fn pool_matrix_builds()
+	{
+		let matrix = PoolConfig::matrix();
+
+		assert!( matrix.contains( &PoolConfig::default() ) );
+
+		for config in matrix
+		{
+			config.create().expect( &config.to_string() );
+		}
+	}
+}
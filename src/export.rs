@@ -0,0 +1,407 @@
+//! Write the results of a run to JSON and CSV files, so dashboards don't have to scrape the
+//! output of criterion.
+//!
+//! Every run writes `<name>-<unix time>.json` and `.csv` in the results directory, and overwrites
+//! `<name>-latest.json` and `.csv`. The directory is `target/results`, unless `BENCH_RESULTS_DIR` is set.
+//!
+use crate::{ AllocReport, AllocStats, BalanceReport, BenchId, Latency, LatencyReport, MemoryReport, OverheadReport, PerfReport, PerfStats, Record, Stats, TaskBalance, TaskMemory, UsageReport, UsageStats, baseline_from_env, results };
+use serde::{ Deserialize, Serialize };
+use std::{ env, fmt::Display, fs, io, path::{ Path, PathBuf }, time::{ SystemTime, UNIX_EPOCH } };
+
+
+// The environment variable to change the directory results are written to.
+//
+pub const RESULTS_DIR_VAR: &str = "BENCH_RESULTS_DIR";
+
+
+// One benchmark in the exported results.
+//
+#[ derive( Clone, Debug, PartialEq, Serialize, Deserialize ) ]
+//
+pub struct Summary
+{
+	#[ serde( flatten ) ] pub id   : BenchId ,
+	#[ serde( flatten ) ] pub stats: Stats   ,
+
+	// Only for workloads that record latencies.
+	//
+	#[ serde( default, skip_serializing_if = "Option::is_none" ) ]
+	//
+	pub latency: Option<Latency>,
+
+	// Only when built with the `count_alloc` feature.
+	//
+	#[ serde( default, skip_serializing_if = "Option::is_none" ) ]
+	//
+	pub allocations: Option<AllocStats>,
+
+	// Only where `getrusage` is available.
+	//
+	#[ serde( default, skip_serializing_if = "Option::is_none" ) ]
+	//
+	pub usage: Option<UsageStats>,
+
+	// Only when built with the `perf_events` feature.
+	//
+	#[ serde( default, skip_serializing_if = "Option::is_none" ) ]
+	//
+	pub perf: Option<PerfStats>,
+
+	// Only for workloads that measure the memory footprint of tasks.
+	//
+	#[ serde( default, skip_serializing_if = "Option::is_none" ) ]
+	//
+	pub memory: Option<TaskMemory>,
+
+	// Only for workloads that count the tasks every thread ran.
+	//
+	#[ serde( default, skip_serializing_if = "Option::is_none" ) ]
+	//
+	pub balance: Option<TaskBalance>,
+}
+
+
+impl Summary
+{
+	pub fn new( record: &Record ) -> Self
+	{
+		Self
+		{
+			id         : record.id.clone()                                                     ,
+			stats      : record.stats()                                                        ,
+			latency    : record.latency.as_ref().map( Latency::new )                           ,
+			allocations: record.allocations.map( |a| AllocStats::new( a, record.iterations ) ) ,
+			usage      : UsageStats::from_record( record )                                     ,
+			perf       : PerfStats::from_record( record )                                      ,
+			memory     : record.memory                                                         ,
+			balance    : record.balance.clone()                                                ,
+		}
+	}
+}
+
+
+// The directory results are written to.
+//
+pub fn results_dir() -> PathBuf
+{
+	env::var_os( RESULTS_DIR_VAR ).map( PathBuf::from ).unwrap_or_else( || PathBuf::from( "target/results" ) )
+}
+
+
+// Serialize the summaries as a JSON array.
+//
+pub fn to_json( summaries: &[Summary] ) -> String
+{
+	serde_json::to_string_pretty( summaries ).expect( "serialize results" )
+}
+
+
+// Serialize the summaries as CSV. There is a column for every parameter that appears in any of the
+// benchmarks, in the order they first appear. It is empty for benchmarks that don't have it.
+// The same goes for the latency, allocation, resource usage, perf counter, memory and balance
+// columns, which are only there if any benchmark has them. Tasks per thread are separated by `;`.
+//
+pub fn to_csv( summaries: &[Summary] ) -> String
+{
+	let mut params: Vec<&str> = Vec::new();
+
+	for s in summaries
+	{
+		for (name, _) in &s.id.params
+		{
+			if !params.contains( &name.as_str() ) { params.push( name ); }
+		}
+	}
+
+	let mut header = vec![ "group", "executor", "workload" ];
+
+	header.extend( &params );
+	header.extend( &[ "count", "mean_ns", "median_ns", "stddev_ns", "min_ns", "p90_ns", "p95_ns", "p99_ns", "max_ns" ] );
+
+	let latency = summaries.iter().any( |s| s.latency.is_some() );
+
+	if latency
+	{
+		header.extend( &[ "latency_count", "latency_p50_ns", "latency_p90_ns", "latency_p99_ns", "latency_p999_ns", "latency_max_ns" ] );
+	}
+
+	let allocations = summaries.iter().any( |s| s.allocations.is_some() );
+
+	if allocations
+	{
+		header.extend( &[ "allocs_per_iter", "alloc_bytes_per_iter" ] );
+	}
+
+	let usage = summaries.iter().any( |s| s.usage.is_some() );
+
+	if usage
+	{
+		header.extend( &[ "user_ns_per_iter", "sys_ns_per_iter", "cpu_ratio", "voluntary_switches_per_iter", "involuntary_switches_per_iter", "minor_faults_per_iter", "major_faults_per_iter" ] );
+	}
+
+	let perf = summaries.iter().any( |s| s.perf.is_some() );
+
+	if perf
+	{
+		header.extend( &[ "task_clock_ns_per_iter", "cpus_utilized", "context_switches_per_iter", "cpu_migrations_per_iter" ] );
+	}
+
+	let memory = summaries.iter().any( |s| s.memory.is_some() );
+
+	if memory
+	{
+		header.extend( &[ "tasks", "rss_bytes_per_task", "heap_bytes_per_task", "peak_rss_bytes" ] );
+	}
+
+	let balance = summaries.iter().any( |s| s.balance.is_some() );
+
+	if balance
+	{
+		header.extend( &[ "imbalance", "tasks_per_thread" ] );
+	}
+
+	let mut out = csv_line( header.into_iter().map( String::from ) );
+
+	for s in summaries
+	{
+		let mut row = vec![ s.id.group.clone(), s.id.executor.clone(), s.id.workload.clone() ];
+
+		row.extend( params.iter().map( |p| s.id.get( p ).unwrap_or_default().to_string() ) );
+
+		let st = &s.stats;
+
+		row.push( st.count.to_string() );
+		row.extend( [ st.mean_ns, st.median_ns, st.stddev_ns, st.min_ns, st.p90_ns, st.p95_ns, st.p99_ns, st.max_ns ].iter().map( f64::to_string ) );
+
+		match &s.latency
+		{
+			Some( l )       => row.extend( [ l.count, l.p50_ns, l.p90_ns, l.p99_ns, l.p999_ns, l.max_ns ].iter().map( u64::to_string ) ),
+			None if latency => row.extend( vec![ String::new(); 6 ] ),
+			None            => {}
+		}
+
+		match &s.allocations
+		{
+			Some( a )           => row.extend( vec![ a.allocs_per_iter.to_string(), a.bytes_per_iter.to_string() ] ),
+			None if allocations => row.extend( vec![ String::new(); 2 ] ),
+			None                => {}
+		}
+
+		match &s.usage
+		{
+			Some( u ) =>
+			{
+				let fields = [ u.user_ns, u.sys_ns, u.cpu_ratio, u.voluntary_switches, u.involuntary_switches, u.minor_faults, u.major_faults ];
+
+				row.extend( fields.iter().map( f64::to_string ) );
+			}
+
+			None if usage => row.extend( vec![ String::new(); 7 ] ),
+			None          => {}
+		}
+
+		match &s.perf
+		{
+			Some( p )    => row.extend( [ p.task_clock_ns, p.cpus_utilized, p.context_switches, p.cpu_migrations ].iter().map( f64::to_string ) ),
+			None if perf => row.extend( vec![ String::new(); 4 ] ),
+			None         => {}
+		}
+
+		match &s.memory
+		{
+			Some( m ) =>
+			{
+				let heap = m.heap_per_task().map( |h| h.to_string() ).unwrap_or_default();
+
+				row.extend( vec![ m.tasks.to_string(), m.rss_per_task().to_string(), heap, m.peak_rss.to_string() ] );
+			}
+
+			None if memory => row.extend( vec![ String::new(); 4 ] ),
+			None           => {}
+		}
+
+		match &s.balance
+		{
+			Some( b ) =>
+			{
+				let tasks: Vec<String> = b.tasks_per_thread.iter().map( u64::to_string ).collect();
+
+				row.extend( vec![ b.imbalance().to_string(), tasks.join( ";" ) ] );
+			}
+
+			None if balance => row.extend( vec![ String::new(); 2 ] ),
+			None            => {}
+		}
+
+		out.push_str( &csv_line( row.into_iter() ) );
+	}
+
+	out
+}
+
+
+// Quote fields that contain a separator, a quote or a newline.
+//
+fn csv_line( fields: impl Iterator<Item=String> ) -> String
+{
+	let fields: Vec<String> = fields.map( |f|
+	{
+		match f.contains( &[ ',', '"', '\n' ][..] )
+		{
+			true  => format!( "\"{}\"", f.replace( '"', "\"\"" ) ),
+			false => f,
+		}
+
+	}).collect();
+
+	fields.join( "," ) + "\n"
+}
+
+
+// Read results written by `write_results`.
+//
+pub fn load_results( path: impl AsRef<Path> ) -> Result< Vec<Summary>, String >
+{
+	let path = path.as_ref();
+	let json = fs::read_to_string( path ).map_err( |e| format!( "read {}: {}", path.display(), e ) )?;
+
+	serde_json::from_str( &json ).map_err( |e| format!( "{}: {}", path.display(), e ) )
+}
+
+
+// Write the summaries of the records to the results directory. Returns the files written.
+//
+pub fn write_results( name: &str, records: &[Record] ) -> io::Result< Vec<PathBuf> >
+{
+	let dir       = results_dir();
+	let summaries = records.iter().map( Summary::new ).collect::<Vec<_>>();
+	let time      = SystemTime::now().duration_since( UNIX_EPOCH ).map( |d| d.as_secs() ).unwrap_or_default();
+	let json      = to_json( &summaries );
+	let csv       = to_csv ( &summaries );
+
+	fs::create_dir_all( &dir )?;
+
+	let mut written = Vec::new();
+
+	for stem in &[ time.to_string(), "latest".to_string() ]
+	{
+		let json_path = dir.join( format!( "{}-{}.json", name, stem ) );
+		let csv_path  = dir.join( format!( "{}-{}.csv" , name, stem ) );
+
+		fs::write( &json_path, &json )?;
+		fs::write( &csv_path , &csv  )?;
+
+		written.push( json_path );
+		written.push( csv_path  );
+	}
+
+	Ok( written )
+}
+
+
+// Write all results recorded in this process and print where they went. A failed export is
+// reported but doesn't fail the run, the timings are still in the output of criterion.
+//
+pub fn export_results( name: &str )
+{
+	export( name, &results() );
+}
+
+
+// What every bench does after criterion is done: print the reports that have something to show,
+// export the records and compare them to the baselines of `bench`. Returns the exit code of
+// `baseline_from_env`.
+//
+pub fn report_and_export( bench: &str, records: &[Record] ) -> i32
+{
+	let print = |empty: bool, report: &dyn Display| if !empty { println!( "\n{}", report ) };
+
+	let overhead = OverheadReport::new( records );
+	let latency  = LatencyReport ::new( records );
+	let allocs   = AllocReport   ::new( records );
+	let usage    = UsageReport   ::new( records );
+	let perf     = PerfReport    ::new( records );
+	let memory   = MemoryReport  ::new( records );
+	let balance  = BalanceReport ::new( records );
+
+	print( overhead.is_empty(), &overhead );
+	print( latency .is_empty(), &latency  );
+	print( allocs  .is_empty(), &allocs   );
+	print( usage   .is_empty(), &usage    );
+	print( perf    .is_empty(), &perf     );
+	print( memory  .is_empty(), &memory   );
+	print( balance .is_empty(), &balance  );
+
+	export( bench, records );
+
+	baseline_from_env( bench, records )
+}
+
+
+fn export( name: &str, records: &[Record] )
+{
+	match write_results( name, records )
+	{
+		Ok ( written ) => println!( "Results written to: {}", written.iter().map( |p| p.display().to_string() ).collect::<Vec<_>>().join( ", " ) ),
+		Err( e       ) => eprintln!( "Failed to write results to {}: {}", results_dir().display(), e ),
+	}
+}
+
+
+
+#[ cfg( test ) ]
+//
+mod tests
+{
+	use super::*;
+	use std::time::Duration;
+
+	fn summaries() -> Vec<Summary>
+	{
+		let record = |executor: &str, params: &[( &str, &str )]|
+		{
+			let mut id = BenchId::new( "Ring", executor, "BoundedRing" );
+
+			for (n, v) in params { id = id.param( *n, v ); }
+
+			Summary::new( &Record::new( id, vec![ Duration::from_nanos( 10 ), Duration::from_nanos( 20 ) ] ) )
+		};
+
+		vec!
+		[
+			record( "TokioTp"                                             , &[ ( "nodes", "10" )                    ] ),
+			record( "tokio::runtime::Runtime (basic_scheduler + LocalSet)", &[ ( "nodes", "10" ), ( "laps", "2" ) ] ),
+		]
+	}
+
+
+	#[test]
+	//
+	fn json_roundtrip()
+	{
+		let summaries = summaries();
+		let json      = to_json( &summaries );
+
+		assert!( json.contains( r#""params": {
+      "nodes": "10",
+      "laps": "2"
+    }"# ) );
+
+		let back: Vec<Summary> = serde_json::from_str( &json ).expect( "parse json" );
+
+		assert_eq!( summaries, back );
+	}
+
+
+	#[test]
+	//
+	fn csv()
+	{
+		let csv   = to_csv( &summaries() );
+		let lines = csv.lines().collect::<Vec<_>>();
+
+		assert_eq!( "group,executor,workload,nodes,laps,count,mean_ns,median_ns,stddev_ns,min_ns,p90_ns,p95_ns,p99_ns,max_ns", lines[0] );
+		assert!( lines[1].starts_with( "Ring,TokioTp,BoundedRing,10,,2,15," ) );
+		assert!( lines[2].starts_with( "Ring,tokio::runtime::Runtime (basic_scheduler + LocalSet),BoundedRing,10,2,2,15," ) );
+	}
+}
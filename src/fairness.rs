@@ -0,0 +1,159 @@
+//! Fairness: mix a few CPU heavy tasks with many short ones and measure how long the short ones
+//! wait between being spawned and their first poll. The waits are recorded with
+//! [`record_latencies`](crate::record_latencies) at the end of the run.
+//!
+//! The heavy tasks burn the CPU in slices and yield in between, like a well behaved task doing a
+//! lot of work. A fair scheduler gets to the short tasks within about one slice, one that keeps
+//! polling the task that just yielded lets them wait much longer. So heavy tasks don't run forever
+//! on such an executor, each of them stops after `MAX_SLICES`.
+//!
+//! The short tasks are spawned from the driving future in waves of `WAVE`, the next wave is spawned
+//! when all tasks of the previous one have reported.
+//!
+use futures::{ channel::oneshot, future::join_all, task::{ Spawn, SpawnExt } };
+use std::{ future::Future, pin::Pin, sync::{ Arc, atomic::{ AtomicBool, Ordering::SeqCst } }, task::{ Context, Poll }, time::{ Duration, Instant } };
+use crate::record_latencies;
+
+
+// How long a heavy task runs before yielding.
+//
+pub const SLICE: Duration = Duration::from_micros( 100 );
+
+// The most slices a heavy task runs, about one second.
+//
+pub const MAX_SLICES: usize = 10_000;
+
+// How many short tasks are spawned at once.
+//
+pub const WAVE: usize = 10;
+
+
+pub struct Fairness
+{
+	short: usize,
+	heavy: usize,
+}
+
+
+impl Fairness
+{
+	// Measure the wait of `short` tasks while `heavy` tasks burn the CPU.
+	//
+	pub fn new( short: usize, heavy: usize ) -> Self
+	{
+		assert!( short > 0 );
+
+		Self { short, heavy }
+	}
+
+
+	pub async fn run( &self, exec: impl Spawn )
+	{
+		let stop = Arc::new( AtomicBool::new( false ) );
+
+		let heavy: Vec<_> = ( 0..self.heavy ).map( |_|
+		{
+			exec.spawn_with_handle( heavy( stop.clone() ) ).expect( "spawn heavy task" )
+
+		}).collect();
+
+		let mut waits = Vec::with_capacity( self.short );
+
+		while waits.len() < self.short
+		{
+			let wave = WAVE.min( self.short - waits.len() );
+
+			let rxs: Vec<_> = ( 0..wave ).map( |_|
+			{
+				let (tx, rx) = oneshot::channel();
+				let spawned  = Instant::now();
+
+				exec.spawn( async move { let _ = tx.send( spawned.elapsed() ); } ).expect( "spawn short task" );
+
+				rx
+
+			}).collect();
+
+			for wait in join_all( rxs ).await
+			{
+				waits.push( wait.expect( "receive wait" ) );
+			}
+		}
+
+		// Don't let the heavy tasks outlive the run.
+		//
+		stop.store( true, SeqCst );
+		join_all( heavy ).await;
+
+		record_latencies( waits.into_iter().map( |w| w.as_nanos() as u64 ) );
+	}
+}
+
+
+// Burn the CPU in slices until told to stop or `MAX_SLICES` have run.
+//
+async fn heavy( stop: Arc<AtomicBool> )
+{
+	for _ in 0..MAX_SLICES
+	{
+		if stop.load( SeqCst ) { return; }
+
+		let start = Instant::now();
+
+		while start.elapsed() < SLICE {}
+
+		YieldNow( false ).await;
+	}
+}
+
+
+// Return pending once, waking ourselves, so the executor can run something else.
+//
+struct YieldNow( bool );
+
+impl Future for YieldNow
+{
+	type Output = ();
+
+	fn poll( mut self: Pin<&mut Self>, cx: &mut Context<'_> ) -> Poll<()>
+	{
+		if self.0 { return Poll::Ready(()); }
+
+		self.0 = true;
+		cx.waker().wake_by_ref();
+
+		Poll::Pending
+	}
+}
+
+
+
+#[ cfg( test ) ]
+//
+mod tests
+{
+	use super::*;
+	use crate::{ LATENCY_TESTS, take_latencies };
+	use futures::executor::{ LocalPool, ThreadPool, block_on };
+
+	#[test]
+	//
+	fn every_short_task_measured()
+	{
+		let _lock = LATENCY_TESTS.lock();
+
+		let pool  = ThreadPool::builder().pool_size( 2 ).create().expect( "create threadpool" );
+		let bench = Fairness::new( 25, 2 );
+
+		take_latencies();
+		block_on( bench.run( pool ) );
+		assert_eq!( 25, take_latencies().expect( "latencies recorded" ).len() );
+
+
+		let mut local   = LocalPool::new();
+		let     spawner = local.spawner();
+
+		local.run_until( bench.run( spawner ) );
+		assert_eq!( 25, take_latencies().expect( "latencies recorded" ).len() );
+	}
+}
@@ -0,0 +1,70 @@
+//! How a node passes a message on to the next node in the ring.
+//!
+use serde::Deserialize;
+use std::{ fmt, str::FromStr };
+
+
+// The forwarding mode of a ring. Every ring implementation supports all modes, so that
+// comparing two executors (or two spawn traits on the same executor) compares the same algorithm.
+//
+// In scenario files, the modes are written as "inline", "detach" and "await".
+//
+#[ derive( Copy, Clone, Debug, PartialEq, Eq, Hash, Deserialize ) ]
+#[ serde( rename_all = "lowercase" ) ]
+//
+pub enum Forward
+{
+	// Send the message from within the node task itself, without spawning.
+	//
+	Inline,
+
+	// Spawn a task that sends the message and don't wait for it.
+	//
+	Detach,
+
+	// Spawn a task that sends the message and await it's completion before reading
+	// the next message.
+	//
+	Await,
+}
+
+
+impl Forward
+{
+	// All forwarding modes, convenient for iterating in benchmarks.
+	//
+	pub const ALL: [Forward; 3] = [ Forward::Inline, Forward::Detach, Forward::Await ];
+}
+
+
+impl fmt::Display for Forward
+{
+	fn fmt( &self, f: &mut fmt::Formatter<'_> ) -> fmt::Result
+	{
+		match self
+		{
+			Forward::Inline => write!( f, "inline" ),
+			Forward::Detach => write!( f, "detach" ),
+			Forward::Await  => write!( f, "await"  ),
+		}
+	}
+}
+
+
+// Parses the names produced by `Display`.
+//
+impl FromStr for Forward
+{
+	type Err = String;
+
+	fn from_str( s: &str ) -> Result<Self, Self::Err>
+	{
+		match s
+		{
+			"inline" => Ok( Forward::Inline ),
+			"detach" => Ok( Forward::Detach ),
+			"await"  => Ok( Forward::Await  ),
+			_        => Err( format!( "unknown forwarding mode: {}, expected inline, detach or await", s ) ),
+		}
+	}
+}
@@ -0,0 +1,163 @@
+//! Latency distributions, for workloads that timestamp events themselves rather than only measuring
+//! the total run time, eg. the hops of [`LatencyRing`](crate::LatencyRing).
+//!
+//! Workloads record their latencies in nanoseconds with `record_latencies`. `timed` collects them
+//! in an HDR histogram on the record of the benchmark, next to the timings. Criterion also runs the
+//! routine to warm up. `keep_last` drops those batches, so only the latencies of the samples are kept.
+//!
+use crate::{ Record, format_ns };
+use hdrhistogram::Histogram;
+use serde::{ Deserialize, Serialize };
+use std::{ fmt, sync::Mutex };
+
+
+static LATENCIES: Mutex< Option< Histogram<u64> > > = Mutex::new( None );
+
+// Latencies are collected globally, so tests that check them must not run in parallel.
+//
+#[ cfg( test ) ]
+//
+pub(crate) static LATENCY_TESTS: Mutex<()> = Mutex::new( () );
+
+
+// An empty histogram with 3 significant figures, that grows to fit the values recorded.
+//
+pub fn latency_histogram() -> Histogram<u64>
+{
+	Histogram::new( 3 ).expect( "3 significant figures are supported" )
+}
+
+
+// Record latencies of the workload that is running, in nanoseconds. Workloads should call this
+// once they are done, so locking doesn't influence the latencies they measure.
+//
+pub fn record_latencies( latencies: impl IntoIterator<Item=u64> )
+{
+	let mut guard     = LATENCIES.lock().expect( "lock latencies" );
+	let     histogram = guard.get_or_insert_with( latency_histogram );
+
+	for latency in latencies
+	{
+		histogram.record( latency ).expect( "histogram grows to fit" );
+	}
+}
+
+
+// Take the latencies recorded since the last call.
+//
+pub fn take_latencies() -> Option< Histogram<u64> >
+{
+	LATENCIES.lock().expect( "lock latencies" ).take()
+}
+
+
+
+// Summary of a latency distribution. All values are in nanoseconds.
+//
+#[ derive( Clone, Debug, PartialEq, Serialize, Deserialize ) ]
+//
+pub struct Latency
+{
+	pub count  : u64 ,
+	pub p50_ns : u64 ,
+	pub p90_ns : u64 ,
+	pub p99_ns : u64 ,
+	pub p999_ns: u64 ,
+	pub max_ns : u64 ,
+}
+
+
+impl Latency
+{
+	pub fn new( histogram: &Histogram<u64> ) -> Self
+	{
+		Self
+		{
+			count  : histogram.len()                      ,
+			p50_ns : histogram.value_at_quantile( 0.5   ) ,
+			p90_ns : histogram.value_at_quantile( 0.9   ) ,
+			p99_ns : histogram.value_at_quantile( 0.99  ) ,
+			p999_ns: histogram.value_at_quantile( 0.999 ) ,
+			max_ns : histogram.max()                      ,
+		}
+	}
+}
+
+
+
+// Prints a table of the latency distributions in a set of records. Records without latencies are skipped.
+//
+pub struct LatencyReport( pub Vec<( String, Latency )> );
+
+
+impl LatencyReport
+{
+	pub fn new( records: &[Record] ) -> Self
+	{
+		Self( records.iter().filter_map( |r| Some(( r.id.to_string(), Latency::new( r.latency.as_ref()? ) )) ).collect() )
+	}
+
+
+	pub fn is_empty( &self ) -> bool
+	{
+		self.0.is_empty()
+	}
+}
+
+
+impl fmt::Display for LatencyReport
+{
+	fn fmt( &self, f: &mut fmt::Formatter<'_> ) -> fmt::Result
+	{
+		let width = self.0.iter().map( |(id, _)| id.len() ).max().unwrap_or( 0 );
+
+		writeln!( f, "Latency distributions:" )?;
+		writeln!( f )?;
+
+		writeln!
+		(
+			f, "  {:<width$}  {:>10}  {:>10}  {:>10}  {:>10}  {:>10}  {:>10}",
+			"", "count", "p50", "p90", "p99", "p99.9", "max", width = width,
+		)?;
+
+		for (id, l) in &self.0
+		{
+			let ns = |ns: u64| format_ns( ns as f64 );
+
+			writeln!
+			(
+				f, "  {:<width$}  {:>10}  {:>10}  {:>10}  {:>10}  {:>10}  {:>10}",
+				id, l.count, ns( l.p50_ns ), ns( l.p90_ns ), ns( l.p99_ns ), ns( l.p999_ns ), ns( l.max_ns ), width = width,
+			)?;
+		}
+
+		Ok(())
+	}
+}
+
+
+
+#[ cfg( test ) ]
+//
+mod tests
+{
+	use super::*;
+
+	#[test]
+	//
+	fn percentiles()
+	{
+		let mut histogram = latency_histogram();
+
+		for ns in 1..=1000 { histogram.record( ns ).expect( "record latency" ); }
+
+		let latency = Latency::new( &histogram );
+
+		assert_eq!( 1000, latency.count   );
+		assert_eq!( 500 , latency.p50_ns  );
+		assert_eq!( 900 , latency.p90_ns  );
+		assert_eq!( 990 , latency.p99_ns  );
+		assert_eq!( 999 , latency.p999_ns );
+		assert_eq!( 1000, latency.max_ns  );
+	}
+}
@@ -0,0 +1,119 @@
+//! A ring like [`BoundedRing`](crate::BoundedRing), but every message carries the time it was forwarded,
+//! so the next node can measure how long the hop took. The latencies of all hops are recorded
+//! with [`record_latencies`](crate::record_latencies) at the end of the run.
+//!
+//! A hop starts when a node forwards a message, so with `Forward::Detach` and `Forward::Await`
+//! it includes spawning the task that sends it.
+//!
+use futures::task::{ LocalSpawn, Spawn };
+use std::time::Instant;
+use crate::{ Forward, Message, Ring, RingConfig, bounded_ring::{ WithLocalNodes, WithSpawn } };
+
+
+// The counter and the time the message was forwarded.
+//
+#[ derive( Copy, Clone ) ]
+//
+struct Timed( usize, Instant );
+
+
+impl Message for Timed
+{
+	const TIMED: bool = true;
+
+	fn forward( count: usize ) -> Self
+	{
+		Self( count, Instant::now() )
+	}
+
+	fn count( self ) -> usize
+	{
+		self.0
+	}
+
+	fn hop( self ) -> u64
+	{
+		self.1.elapsed().as_nanos() as u64
+	}
+}
+
+
+
+pub struct LatencyRing( Ring<Timed> );
+
+
+impl LatencyRing
+{
+	// Create channels between all the nodes.
+	//
+	pub fn new( n: usize, forward: Forward ) -> Self
+	{
+		Self::with_config( RingConfig::new( n, forward ) )
+	}
+
+
+	// Create a ring with a custom channel capacity and number of laps.
+	//
+	pub fn with_config( config: RingConfig ) -> Self
+	{
+		Self( Ring::with_config( config ) )
+	}
+
+
+	// Run the benchmark.
+	//
+	pub async fn run( &mut self, exec: impl Spawn + Clone + Send + Sync + 'static )
+	{
+		self.0.run( WithSpawn( exec ) ).await;
+	}
+
+
+	// Run the benchmark on a local pool.
+	//
+	pub async fn run_local( &mut self, exec: impl LocalSpawn + Spawn + Clone + 'static )
+	{
+		self.0.run( WithLocalNodes( exec ) ).await;
+	}
+}
+
+
+
+#[ cfg( test ) ]
+//
+mod tests
+{
+	use super::*;
+	use crate::{ LATENCY_TESTS, take_latencies };
+	use futures::executor::{ LocalPool, ThreadPool, block_on };
+
+	// Every node measures every hop of every message, in all forwarding modes.
+	//
+	#[test]
+	//
+	fn every_hop_measured()
+	{
+		let _lock = LATENCY_TESTS.lock();
+
+		let pool = ThreadPool::new().expect( "create threadpool" );
+
+		for forward in Forward::ALL.iter()
+		{
+			let mut ring = LatencyRing::with_config( RingConfig::new( 10, *forward ).laps( 2 ) );
+
+			take_latencies();
+			block_on( ring.run( pool.clone() ) );
+
+			assert_eq!( 10 * 10 * 2, take_latencies().expect( "latencies recorded" ).len() );
+		}
+
+
+		let mut local   = LocalPool::new();
+		let     spawner = local.spawner();
+		let mut ring    = LatencyRing::new( 5, Forward::Detach );
+
+		take_latencies();
+		local.run_until( ring.run_local( spawner ) );
+
+		assert_eq!( 5 * 5, take_latencies().expect( "latencies recorded" ).len() );
+	}
+}
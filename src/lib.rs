@@ -1,4 +1,7 @@
 
+pub mod forward                ;
+pub mod ring_config            ;
+pub mod ring                   ;
 pub mod bounded_ring           ;
 pub mod local_ring             ;
 pub mod async_std_bounded_ring ;
@@ -6,10 +9,43 @@ pub mod tokio_tp_bounded_ring  ;
 pub mod tokio_ct_bounded_ring  ;
 pub mod handle_ring            ;
 pub mod local_handle_ring      ;
+pub mod threads                ;
+pub mod exec_config            ;
+pub mod affinity               ;
+pub mod smol_exec              ;
+pub mod smol_native_ring       ;
+pub mod smol_local_native_ring ;
+pub mod threadpool_native_ring ;
+pub mod local_pool_native_ring ;
+pub mod mini_exec              ;
+pub mod drive                  ;
+pub mod results                ;
+pub mod overhead               ;
+pub mod routine                ;
+pub mod scenario               ;
+pub mod stats                  ;
+pub mod export                 ;
+pub mod table                  ;
+pub mod baseline               ;
+pub mod latency                ;
+pub mod latency_ring           ;
+pub mod spawn_latency          ;
+pub mod wake_latency           ;
+pub mod fairness               ;
+pub mod work_stealing          ;
+pub mod alloc_counter          ;
+pub mod task_memory            ;
+pub mod rusage                 ;
+pub mod perf_counters          ;
+pub mod verify                 ;
+pub mod watchdog               ;
 
 
 pub use
 {
+	forward                :: * ,
+	ring_config            :: * ,
+	ring                   :: * ,
 	bounded_ring           :: * ,
 	local_ring             :: * ,
 	handle_ring            :: * ,
@@ -17,4 +53,34 @@ pub use
 	async_std_bounded_ring :: * ,
 	tokio_tp_bounded_ring  :: * ,
 	tokio_ct_bounded_ring  :: * ,
+	threads                :: * ,
+	exec_config            :: * ,
+	affinity               :: * ,
+	smol_exec              :: * ,
+	smol_native_ring       :: * ,
+	smol_local_native_ring :: * ,
+	threadpool_native_ring :: * ,
+	local_pool_native_ring :: * ,
+	mini_exec              :: * ,
+	drive                  :: * ,
+	results                :: * ,
+	overhead               :: * ,
+	routine                :: * ,
+	scenario               :: * ,
+	stats                  :: * ,
+	export                 :: * ,
+	table                  :: * ,
+	baseline               :: * ,
+	latency                :: * ,
+	latency_ring           :: * ,
+	spawn_latency          :: * ,
+	wake_latency           :: * ,
+	fairness               :: * ,
+	work_stealing          :: * ,
+	alloc_counter          :: * ,
+	task_memory            :: * ,
+	rusage                 :: * ,
+	perf_counters          :: * ,
+	verify                 :: * ,
+	watchdog               :: * ,
 };
@@ -1,194 +1,72 @@
 //! This uses bounded channels and has the possibility to spawn each message forwarding on the executor
-//! rather than awaiting.
+//! rather than awaiting. See [`Forward`](crate::Forward).
 //!
-use futures::{ future::join_all, SinkExt, StreamExt, channel::mpsc };
-use std::{ sync::{ atomic::{ AtomicUsize, Ordering } } };
-use log::*;
+//! Only `Forward::Await` goes through `LocalSpawnHandle`. Nodes and detached tasks are spawned like in
+//! [`LocalRing`](crate::LocalRing), so the other modes run the same code as it does. See `in_default_matrix`.
+//!
+use futures::{ channel::mpsc, task::{ LocalSpawn, LocalSpawnExt } };
+use std::future::Future;
 use async_executors::*;
-
-static DONE: AtomicUsize = AtomicUsize::new( 0 );
+use crate::{ Forward, Message, Node, Ring, RingConfig, RingSpawn };
 
 
-pub struct LocalHandleRing
-{
-	nodes: Option< Vec<LocalHandleNode> > ,
-}
+pub struct LocalHandleRing( Ring );
 
 
 impl LocalHandleRing
 {
 	// Create channels between all the nodes.
 	//
-	pub fn new( n: usize ) -> Self
+	pub fn new( n: usize, forward: Forward ) -> Self
 	{
-		assert!( n > 1 );
-
-		let mut nodes = Vec::with_capacity( n );
-
-		// The channel always has one slot per sender, so this is a bounded
-		// channel of size channel_size + 1. I choose a low number in the hopes
-		// that some tasks will block, creating a bit more work for the executor
-		// and a more realistic benchmark.
-		//
-		let channel_size = 1;
-
-		// The connection between the last and the first node.
-		//
-		let (last_tx, last_rx) = mpsc::channel( channel_size );
-
-		// The first node
-		//
-		let (tx, mut next_rx) = mpsc::channel( channel_size );
-
-		nodes.push( LocalHandleNode { id: 1, n, tx, rx: last_rx } );
-
-
-		// All but first and last.
-		// Note that 1..1 does not do anything, so it works if n==2.
-		//
-		for id in 2..n
-		{
-			let (tx, rx) = mpsc::channel( channel_size );
-
-			nodes.push( LocalHandleNode { id, n, tx, rx: next_rx } );
-
-			next_rx = rx;
-		}
-
+		Self::with_config( RingConfig::new( n, forward ) )
+	}
 
-		// The last node
-		//
-		nodes.push( LocalHandleNode { id: n, n, tx: last_tx, rx: next_rx } );
 
-		Self
-		{
-			nodes: Some( nodes ),
-		}
+	// Create a ring with a custom channel capacity and number of laps.
+	//
+	pub fn with_config( config: RingConfig ) -> Self
+	{
+		Self( Ring::with_config( config ) )
 	}
 
 
 	// Run the benchmark.
 	//
-	pub async fn run( &mut self, exec: impl LocalSpawnHandle<()> + Clone + 'static )
+	pub async fn run( &mut self, exec: impl LocalSpawnHandle<()> + LocalSpawn + Clone + 'static )
 	{
-		debug!( "LocalHandleRing: start" );
-
-		// Need to reset the DONE counter, since it might be reused on repeated benchmarks.
-		//
-		DONE.store( 0, Ordering::SeqCst );
-
-
-		let mut handles = Vec::with_capacity( self.nodes.as_ref().unwrap().len() );
-
-		for mut node in self.nodes.take().unwrap().into_iter()
-		{
-			let exec2 = exec.clone();
-			handles.push( exec.spawn_handle_local( async move { node.run( exec2 ).await; } ).expect( "spawn node" ) );
-		};
-
-		join_all( handles ).await;
-
-		debug!( "LocalHandleRing: end" );
+		self.0.run( WithLocalSpawnHandle( exec ) ).await;
 	}
 }
 
 
-// Each node will start by sending a 1 to the next node. When the counter has come back and
-// been incremented by all nodes, the operation is complete.
-//
-pub struct LocalHandleNode
-{
-	id: usize                 ,
-	n : usize                 ,
-	tx: mpsc::Sender  <usize> ,
-	rx: mpsc::Receiver<usize> ,
-}
-
-impl LocalHandleNode
-{
-	async fn run( &mut self, exec: impl LocalSpawnHandle<()> )
-	{
-		debug!( "LocalHandleNode {}: run", self.id );
-
-		let mut tx = self.tx.clone();
-		exec.spawn_handle_local( async move { tx.send( 1 ).await.expect( "Node: send initial message" ); } )
-
-			.expect( "spawn forward" ).await;
-
-		debug!( "LocalHandleNode {}: start loop", self.id );
-
-		// Two ways out of this loop:
-		// - if we are the last node to finish, we break and then close our channel.
-		// - if we aren't the last, we will end the loop when the channel is closed and returns None.
-		//
-		while let Some(msg) = self.rx.next().await
-		{
-			debug_assert!( !msg > self.n );
-
-			// When our message comes back, it should be counted by everyone, so it should be n.
-			// if the msg < n, it didn't originate here.
-			//
-			if msg == self.n
-			{
-				trace!( "LocalHandleNode {}: received our own message back", self.id );
 
-				// Store the fact that we are done.
-				//
-				let old = DONE.fetch_add( 1, Ordering::SeqCst );
-
-				// If we are the last one.
-				//
-				if old+1 == self.n
-				{
-					trace!( "LocalHandleNode {}: all done", self.id );
-
-					break;
-				}
-
-				// else continue passing on messages for the other nodes.
-				//
-				continue;
-			}
-
-			trace!( "LocalHandleNode {}: forwarding a message", self.id );
-
-			// forward the message
-			//
-			let mut tx = self.tx.clone();
-
-			exec.spawn_handle_local( async move { tx.send( msg + 1 ).await.expect( "LocalHandleNode: forward message" ); } )
+// Awaits the forwarding tasks through `LocalSpawnHandle`. A JoinHandle backed by a RemoteHandle cancels
+// it's task when dropped, so detaching goes through `LocalSpawn` rather than dropping the handle.
+//
+#[ derive( Clone ) ]
+//
+struct WithLocalSpawnHandle<S>( S );
 
-				.expect( "spawn forward" ).await;
-		}
 
-		trace!( "LocalHandleNode {}: close our sender", self.id );
+impl<S> RingSpawn for WithLocalSpawnHandle<S>
 
-		// Allow nodes that where still passing on nodes to detect that we are done.
-		//
-		self.tx.close().await.expect( "close channel" );
+	where S: LocalSpawnHandle<()> + LocalSpawn + Clone + 'static
+{
+	type Handle = JoinHandle<()>;
 
-		debug!( "LocalHandleNode {}: run END", self.id );
+	fn spawn_node<M: Message>( &self, node: Node<M>, done: mpsc::Sender<()> )
+	{
+		self.0.spawn_local( node.run( self.clone(), done ) ).expect( "spawn node" );
 	}
-}
 
-
-
-#[ cfg( test ) ]
-//
-mod tests
-{
-	#[ allow( unused_imports ) ] // false positive
-	//
-	use super::*;
-
-	#[test]
-	//
-	fn off_by_one_or_not()
+	fn detach( &self, task: impl Future<Output = ()> + Send + 'static )
 	{
-		let ring2 = LocalHandleRing::new( 2 );
-		assert_eq!( 2, ring2.nodes.unwrap().len() );
+		self.0.spawn_local( task ).expect( "spawn forward" );
+	}
 
-		let ring2 = LocalHandleRing::new( 3 );
-		assert_eq!( 3, ring2.nodes.unwrap().len() );
+	fn spawn_awaited( &self, task: impl Future<Output = ()> + Send + 'static ) -> Self::Handle
+	{
+		self.0.spawn_handle_local( task ).expect( "spawn forward" )
 	}
 }
@@ -0,0 +1,89 @@
+//! This uses bounded channels and has the possibility to spawn each message forwarding on the executor
+//! rather than awaiting. See [`Forward`](crate::Forward).
+//!
+//! Only uses the API of the pool's spawner, `spawn_local` and `spawn_local_with_handle`, not the
+//! `LocalSpawnHandle` trait of async_executors.
+//!
+use futures::{ channel::mpsc, executor::LocalSpawner, future::RemoteHandle, task::LocalSpawnExt };
+use std::future::Future;
+use crate::{ Forward, Message, Node, Ring, RingConfig, RingSpawn };
+
+
+pub struct LocalPoolNativeRing( Ring );
+
+
+impl LocalPoolNativeRing
+{
+	// Create channels between all the nodes.
+	//
+	pub fn new( n: usize, forward: Forward ) -> Self
+	{
+		Self::with_config( RingConfig::new( n, forward ) )
+	}
+
+
+	// Create a ring with a custom channel capacity and number of laps.
+	//
+	pub fn with_config( config: RingConfig ) -> Self
+	{
+		Self( Ring::with_config( config ) )
+	}
+
+
+	// Run the benchmark.
+	//
+	pub async fn run( &mut self, exec: LocalSpawner )
+	{
+		self.0.run( exec ).await;
+	}
+}
+
+
+
+impl RingSpawn for LocalSpawner
+{
+	type Handle = RemoteHandle<()>;
+
+	fn spawn_node<M: Message>( &self, node: Node<M>, done: mpsc::Sender<()> )
+	{
+		self.spawn_local( node.run( self.clone(), done ) ).expect( "spawn node" );
+	}
+
+	fn detach( &self, task: impl Future<Output = ()> + Send + 'static )
+	{
+		self.spawn_local( task ).expect( "spawn forward" );
+	}
+
+	fn spawn_awaited( &self, task: impl Future<Output = ()> + Send + 'static ) -> Self::Handle
+	{
+		self.spawn_local_with_handle( task ).expect( "spawn forward" )
+	}
+}
+
+
+
+#[ cfg( test ) ]
+//
+mod tests
+{
+	#[ allow( unused_imports ) ] // false positive
+	//
+	use super::*;
+	use futures::executor::LocalPool;
+
+	// Every forwarding mode should terminate on a single threaded executor.
+	//
+	#[test]
+	//
+	fn all_forward_modes()
+	{
+		let mut pool = LocalPool::new();
+
+		for forward in Forward::ALL.iter()
+		{
+			let mut ring = LocalPoolNativeRing::new( 10, *forward );
+
+			pool.run_until( ring.run( pool.spawner() ) );
+		}
+	}
+}
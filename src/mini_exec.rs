@@ -0,0 +1,310 @@
+//! A minimal single threaded executor, to serve as a baseline. It has a run queue of task ids
+//! and an `ArcWake` waker per task, and nothing else. Comparing to it shows the floor cost of
+//! scheduling and makes the overhead of the real executors interpretable.
+//!
+use futures::{ pin_mut, future::{ Future, FutureExt, FutureObj, LocalFutureObj, RemoteHandle }, task::{ waker, ArcWake, LocalSpawn, Spawn, SpawnError } };
+use std::{ cell::RefCell, collections::VecDeque, rc::Rc, sync::{ Arc, Mutex, atomic::{ AtomicBool, Ordering } }, task::{ Context, Poll, Waker }, thread::{ self, Thread } };
+
+
+// The id used in the run queue for the future passed to `run_until`.
+//
+const MAIN: usize = usize::max_value();
+
+
+// The executor. Tasks are only polled while `run_until` runs.
+//
+pub struct MiniExec
+{
+	tasks  : Vec< Option<Task> >                 ,
+	free   : Vec< usize >                        ,
+	buffer : Vec< LocalFutureObj<'static, ()> >  ,
+	shared : Arc< Shared >                       ,
+	spawner: MiniSpawner                         ,
+}
+
+
+struct Task
+{
+	future: LocalFutureObj<'static, ()> ,
+	wake  : Arc< TaskWake >             ,
+	waker : Waker                       ,
+}
+
+
+// The run queue and the thread to unpark. Wakers might be called from other threads.
+//
+struct Shared
+{
+	queue : Mutex< VecDeque<usize> > ,
+	thread: Thread                   ,
+}
+
+
+struct TaskWake
+{
+	id    : usize        ,
+	queued: AtomicBool   ,
+	shared: Arc<Shared>  ,
+}
+
+
+impl ArcWake for TaskWake
+{
+	fn wake_by_ref( arc_self: &Arc<Self> )
+	{
+		// Don't queue a task more than once.
+		//
+		if !arc_self.queued.swap( true, Ordering::AcqRel )
+		{
+			arc_self.shared.queue.lock().expect( "lock run queue" ).push_back( arc_self.id );
+			arc_self.shared.thread.unpark();
+		}
+	}
+}
+
+
+impl MiniExec
+{
+	// The executor must be run on the thread that creates it.
+	//
+	pub fn new() -> Self
+	{
+		Self
+		{
+			tasks  : Vec::new()                                  ,
+			free   : Vec::new()                                  ,
+			buffer : Vec::new()                                  ,
+			spawner: MiniSpawner { incoming: Default::default() },
+
+			shared : Arc::new( Shared
+			{
+				queue : Mutex::new( VecDeque::new() ) ,
+				thread: thread::current()             ,
+			}),
+		}
+	}
+
+
+	// A handle to spawn tasks on this executor.
+	//
+	pub fn spawner( &self ) -> MiniSpawner
+	{
+		self.spawner.clone()
+	}
+
+
+	// Run the executor until the future completes. Tasks that are still pending at that
+	// point stay on the executor and will be polled by the next call to `run_until`.
+	//
+	pub fn run_until<F: Future>( &mut self, future: F ) -> F::Output
+	{
+		pin_mut!( future );
+
+		let main = Arc::new( TaskWake { id: MAIN, queued: AtomicBool::new( true ), shared: self.shared.clone() } );
+		let main_waker = waker( main.clone() );
+
+		self.shared.queue.lock().expect( "lock run queue" ).push_back( MAIN );
+
+		loop
+		{
+			self.spawn_incoming();
+
+			let next = self.shared.queue.lock().expect( "lock run queue" ).pop_front();
+
+			match next
+			{
+				Some( MAIN ) =>
+				{
+					main.queued.store( false, Ordering::Release );
+
+					if let Poll::Ready( out ) = future.as_mut().poll( &mut Context::from_waker( &main_waker ) )
+					{
+						return out;
+					}
+				}
+
+				Some( id ) => self.poll_task( id ),
+
+				// Spurious wake ups are fine, we just check the queue again.
+				//
+				None => thread::park(),
+			}
+		}
+	}
+
+
+	// Move tasks spawned since the last call into the task list and schedule them.
+	// Swapping buffers avoids holding the borrow while scheduling and allocating on every call.
+	//
+	fn spawn_incoming( &mut self )
+	{
+		std::mem::swap( &mut self.buffer, &mut *self.spawner.incoming.borrow_mut() );
+
+		for future in self.buffer.drain(..)
+		{
+			let id = match self.free.pop()
+			{
+				Some( id ) => id,
+
+				None =>
+				{
+					self.tasks.push( None );
+					self.tasks.len() - 1
+				}
+			};
+
+			let wake  = Arc::new( TaskWake { id, queued: AtomicBool::new( true ), shared: self.shared.clone() } );
+			let waker = waker( wake.clone() );
+
+			self.tasks[id] = Some( Task { future, wake, waker } );
+			self.shared.queue.lock().expect( "lock run queue" ).push_back( id );
+		}
+	}
+
+
+	fn poll_task( &mut self, id: usize )
+	{
+		// The slot might be empty or reused if a waker was called after the task completed.
+		// Polling the new task spuriously is harmless.
+		//
+		let done = match self.tasks.get_mut( id )
+		{
+			Some( Some( task ) ) =>
+			{
+				task.wake.queued.store( false, Ordering::Release );
+
+				task.future.poll_unpin( &mut Context::from_waker( &task.waker ) ).is_ready()
+			}
+
+			_ => false,
+		};
+
+		if done
+		{
+			self.tasks[id] = None;
+			self.free.push( id );
+		}
+	}
+}
+
+
+impl Default for MiniExec
+{
+	fn default() -> Self
+	{
+		Self::new()
+	}
+}
+
+
+
+// Spawns tasks on a `MiniExec`. Tasks are picked up by the executor the next time it looks at the
+// run queue, which avoids borrowing the task list while it's polling.
+//
+#[ derive( Clone ) ]
+//
+pub struct MiniSpawner
+{
+	incoming: Rc< RefCell< Vec< LocalFutureObj<'static, ()> > > >,
+}
+
+
+impl MiniSpawner
+{
+	// Spawn a future and get a handle to await it's output. Dropping the handle cancels the task.
+	//
+	// MiniSpawner can't implement `SpawnHandle`, as async_executors' `JoinHandle` can't be created
+	// outside of that crate. This returns a futures `RemoteHandle` instead, hence the name.
+	//
+	pub fn spawn_with_remote_handle<Fut>( &self, future: Fut ) -> Result< RemoteHandle<Fut::Output>, SpawnError >
+
+		where Fut: Future + 'static,
+	{
+		let (remote, handle) = future.remote_handle();
+
+		self.spawn_local_obj( LocalFutureObj::new( Box::new( remote ) ) )?;
+
+		Ok( handle )
+	}
+}
+
+
+impl LocalSpawn for MiniSpawner
+{
+	fn spawn_local_obj( &self, future: LocalFutureObj<'static, ()> ) -> Result<(), SpawnError>
+	{
+		self.incoming.borrow_mut().push( future );
+
+		Ok(())
+	}
+}
+
+
+impl Spawn for MiniSpawner
+{
+	fn spawn_obj( &self, future: FutureObj<'static, ()> ) -> Result<(), SpawnError>
+	{
+		self.spawn_local_obj( future.into() )
+	}
+}
+
+
+
+#[ cfg( test ) ]
+//
+mod tests
+{
+	use super::*;
+	use crate::{ BoundedRing, LocalRing, Forward };
+	use futures::channel::oneshot;
+
+	#[test]
+	//
+	fn runs_rings()
+	{
+		let mut exec    = MiniExec::new();
+		let     spawner = exec.spawner();
+
+		for forward in Forward::ALL.iter()
+		{
+			let mut ring = LocalRing::new( 10, *forward );
+			exec.run_until( ring.run( spawner.clone() ) );
+
+			let mut ring = BoundedRing::new( 10, *forward );
+			exec.run_until( ring.run_local( spawner.clone() ) );
+		}
+	}
+
+
+	#[test]
+	//
+	fn spawn_with_remote_handle()
+	{
+		let mut exec   = MiniExec::new();
+		let     handle = exec.spawner().spawn_with_remote_handle( async { 5 } ).expect( "spawn" );
+
+		assert_eq!( 5, exec.run_until( handle ) );
+	}
+
+
+	// Make sure we get unparked when a task is woken from another thread.
+	//
+	#[test]
+	//
+	fn wake_from_other_thread()
+	{
+		let mut exec     = MiniExec::new();
+		let     (tx, rx) = oneshot::channel();
+		let     handle   = exec.spawner().spawn_with_remote_handle( rx ).expect( "spawn" );
+
+		let sender = thread::spawn( move ||
+		{
+			thread::sleep( std::time::Duration::from_millis( 10 ) );
+			tx.send( 3 ).expect( "send" );
+		});
+
+		assert_eq!( Ok( 3 ), exec.run_until( handle ) );
+
+		sender.join().expect( "join sender" );
+	}
+}
@@ -0,0 +1,238 @@
+//! Compare executors wrapped by async_executors to the same executor used through it's native API.
+//!
+//! The native rings and the rings the wrappers run are the same [`Ring`](crate::Ring), with the
+//! same nodes and the same termination, they only spawn through a different API. So the ratio is
+//! the cost of the wrapper and the spawn trait it implements.
+//!
+//! async_executors doesn't wrap the smol executors, they run the generic rings through the adapters
+//! of this crate, see the `smol_exec` module. Their overhead is reported apart, as it says nothing
+//! about async_executors.
+//!
+use crate::{ BenchId, Record };
+use std::fmt;
+
+
+// Pairs of a native ring and the name of the executor that wraps the same runtime, optionally
+// restricted to one workload of the wrapper. Without one, the wrapper is compared on every
+// workload, since it can run several (eg. Spawn and SpawnHandle).
+//
+// The native `LocalPool` ring spawns with `spawn_local` and `spawn_local_with_handle` on it's
+// spawner, which is also what `LocalRing` does on the `LocalPool` executor. So the wrapper is
+// only compared on `LocalHandleRing`, which goes through it's `LocalSpawnHandle` impl.
+//
+pub const WRAPPER_PAIRS: &[( &str, &str, Option<&str> )] =
+&[
+	( "TokioTpNativeRing"   , "TokioTp"   , None                      ),
+	( "TokioCtNativeRing"   , "TokioCt"   , None                      ),
+	( "AsyncStdNativeRing"  , "AsyncStd"  , None                      ),
+	( "ThreadPoolNativeRing", "ThreadPool", None                      ),
+	( "LocalPoolNativeRing" , "LocalPool" , Some( "LocalHandleRing" ) ),
+];
+
+
+// Pairs of a native ring and the adapter of this crate that runs the generic rings on the same
+// executor, like `WRAPPER_PAIRS`.
+//
+pub const ADAPTER_PAIRS: &[( &str, &str, Option<&str> )] =
+&[
+	( "SmolNativeRing"     , "SmolTp"   , None ),
+	( "SmolLocalNativeRing", "SmolLocal", None ),
+];
+
+
+// The overhead of a wrapper compared to the native run with the same parameters.
+//
+#[ derive( Clone, Debug ) ]
+//
+pub struct Overhead
+{
+	pub native : BenchId ,
+	pub wrapper: BenchId ,
+
+	// Whether the wrapper is one of `ADAPTER_PAIRS` rather than an async_executors wrapper.
+	//
+	pub adapter: bool,
+
+	// Mean time of the wrapper divided by the mean time of the native run.
+	//
+	pub ratio: f64,
+}
+
+
+// Find all pairs in the records and compute their overhead.
+//
+pub fn overhead( records: &[Record] ) -> Vec<Overhead>
+{
+	let mut out = Vec::new();
+
+	let pairs = WRAPPER_PAIRS.iter().map( |p| ( p, false ) ).chain( ADAPTER_PAIRS.iter().map( |p| ( p, true ) ) );
+
+	for native in records
+	{
+		for ((native_workload, wrapper, workload), adapter) in pairs.clone()
+		{
+			if native.id.workload != *native_workload { continue }
+
+			for wrapped in records
+			{
+				if wrapped.id.executor != *wrapper
+				|| workload.iter().any( |w| wrapped.id.workload != *w )
+				|| wrapped.id.group    != native.id.group
+				|| wrapped.id.params   != native.id.params
+				{
+					continue
+				}
+
+				let native_mean = native.mean().as_secs_f64();
+
+				if native_mean == 0.0 { continue }
+
+				out.push( Overhead
+				{
+					native : native .id.clone()                          ,
+					wrapper: wrapped.id.clone()                          ,
+					adapter                                              ,
+					ratio  : wrapped.mean().as_secs_f64() / native_mean ,
+				});
+			}
+		}
+	}
+
+	out
+}
+
+
+
+// Prints a table of all the overheads found in a set of records.
+//
+pub struct OverheadReport( pub Vec<Overhead> );
+
+
+impl OverheadReport
+{
+	pub fn new( records: &[Record] ) -> Self
+	{
+		Self( overhead( records ) )
+	}
+
+
+	pub fn is_empty( &self ) -> bool
+	{
+		self.0.is_empty()
+	}
+}
+
+
+impl fmt::Display for OverheadReport
+{
+	fn fmt( &self, f: &mut fmt::Formatter<'_> ) -> fmt::Result
+	{
+		if self.0.is_empty()
+		{
+			return writeln!( f, "No native/wrapper pairs found to compute overhead." );
+		}
+
+		let wrappers: Vec<String> = self.0.iter().map( |o| o.wrapper.to_string() ).collect();
+		let width = wrappers.iter().map( String::len ).max().unwrap_or( 0 );
+
+		let sections =
+		[
+			( false, "Wrapper overhead (mean time of the async_executors wrapper / mean time of the native API):" ),
+			( true , "Adapter overhead (mean time of the smol adapters of this crate / mean time of the native API):" ),
+		];
+
+		for (i, (adapter, title)) in sections.iter().filter( |(a, _)| self.0.iter().any( |o| o.adapter == *a ) ).enumerate()
+		{
+			if i > 0 { writeln!( f )?; }
+
+			writeln!( f, "{}", title )?;
+			writeln!( f )?;
+
+			for (o, wrapper) in self.0.iter().zip( &wrappers ).filter( |(o, _)| o.adapter == *adapter )
+			{
+				writeln!( f, "  {:<width$}  {:>6.3}  (vs {})", wrapper, o.ratio, o.native, width = width )?;
+			}
+		}
+
+		Ok(())
+	}
+}
+
+
+
+#[ cfg( test ) ]
+//
+mod tests
+{
+	use super::*;
+	use std::time::Duration;
+
+	fn record( executor: &str, workload: &str, nodes: usize, ms: u64 ) -> Record
+	{
+		Record::new( BenchId::new( "Ring", executor, workload ).param( "nodes", nodes ), vec![ Duration::from_millis( ms ) ] )
+	}
+
+
+	#[test]
+	//
+	fn pairs_with_same_params()
+	{
+		let records = vec!
+		[
+			record( "tokio::runtime::Runtime", "TokioTpNativeRing", 10, 10 ),
+			record( "TokioTp"                , "BoundedRing"      , 10, 12 ),
+			record( "TokioTp"                , "HandleRing"       , 10, 15 ),
+			record( "TokioTp"                , "HandleRing"       , 20, 30 ),
+			record( "AsyncStd"               , "HandleRing"       , 10, 30 ),
+		];
+
+		let overhead = overhead( &records );
+
+		assert_eq!( 2, overhead.len() );
+		assert_eq!( "TokioTp BoundedRing 10", overhead[0].wrapper.to_string() );
+		assert!( ( overhead[0].ratio - 1.2 ).abs() < 1e-9 );
+		assert!( ( overhead[1].ratio - 1.5 ).abs() < 1e-9 );
+	}
+
+
+	#[test]
+	//
+	fn pairs_restricted_to_workload()
+	{
+		let records = vec!
+		[
+			record( "futures::executor::LocalPool", "LocalPoolNativeRing", 10, 10 ),
+			record( "LocalPool"                   , "LocalRing"          , 10, 11 ),
+			record( "LocalPool"                   , "LocalHandleRing"    , 10, 13 ),
+		];
+
+		let overhead = overhead( &records );
+
+		assert_eq!( 1, overhead.len() );
+		assert_eq!( "LocalPool LocalHandleRing 10", overhead[0].wrapper.to_string() );
+		assert!( ( overhead[0].ratio - 1.3 ).abs() < 1e-9 );
+	}
+
+
+	#[test]
+	//
+	fn adapters_apart()
+	{
+		let records = vec!
+		[
+			record( "tokio::runtime::Runtime" , "TokioTpNativeRing", 10, 10 ),
+			record( "TokioTp"                 , "BoundedRing"      , 10, 12 ),
+			record( "async_executor::Executor", "SmolNativeRing"   , 10, 10 ),
+			record( "SmolTp"                  , "BoundedRing"      , 10, 11 ),
+		];
+
+		let report = OverheadReport::new( &records );
+
+		assert_eq!( vec![ false, true ], report.0.iter().map( |o| o.adapter ).collect::<Vec<_>>() );
+
+		let text = report.to_string();
+
+		assert!( text.find( "TokioTp" ).unwrap() < text.find( "Adapter overhead" ).unwrap() );
+		assert!( text.find( "Adapter overhead" ).unwrap() < text.find( "SmolTp" ).unwrap() );
+	}
+}
@@ -0,0 +1,368 @@
+//! Linux `perf_event_open` software counters: task clock, context switches and CPU migrations, to
+//! report them per benchmark iteration next to the timings. They need no special hardware, so they
+//! also work in virtual machines.
+//!
+//! With the `perf_events` feature, `timed` opens the counters on every thread of the process before
+//! a batch, which includes the worker threads of the executors, and reads them afterwards. Threads
+//! spawned by a counted thread during the batch are counted too, once they exit.
+//!
+//! The kernel must allow it, see `/proc/sys/kernel/perf_event_paranoid`. If it doesn't, a warning
+//! is printed once and the counters are left out. At level 2, the default of many distributions,
+//! unprivileged users may only count user space. Then, like `perf stat`, the counters are opened again
+//! with `exclude_kernel` and `exclude_hv`, and a note is printed once. The task clock counts the same
+//! either way, but context switches and CPU migrations happen in the kernel, so they read 0.
+//!
+//! `cargo run --release --features perf_events --bin runner -- ThreadPool BoundedRing`
+//!
+use crate::Record;
+use serde::{ Deserialize, Serialize };
+use std::{ fmt, fs::File, io::Read, time::Duration };
+
+
+// Software counters of a batch. The task clock is in nanoseconds.
+//
+#[ derive( Clone, Copy, Debug, Default, PartialEq, Eq ) ]
+//
+pub struct PerfCounters
+{
+	pub task_clock_ns   : u64 ,
+	pub context_switches: u64 ,
+	pub cpu_migrations  : u64 ,
+}
+
+
+impl PerfCounters
+{
+	// Add the counters of `other`.
+	//
+	pub fn add( &mut self, other: PerfCounters )
+	{
+		self.task_clock_ns    += other.task_clock_ns    ;
+		self.context_switches += other.context_switches ;
+		self.cpu_migrations   += other.cpu_migrations   ;
+	}
+}
+
+
+
+// Counters opened on all threads of the process. They count from when they are opened.
+//
+pub struct PerfSession
+{
+	// Task clock, context switches and CPU migrations of every thread.
+	//
+	threads: Vec<[ File; 3 ]>,
+}
+
+
+impl PerfSession
+{
+	// Open the counters on every thread of the process. `None` if the `perf_events` feature is
+	// disabled or the counters can't be opened.
+	//
+	#[ cfg( all( feature = "perf_events", target_os = "linux" ) ) ]
+	//
+	pub fn open() -> Option<Self>
+	{
+		use perf_event_open_sys::bindings::*;
+		use std::convert::TryFrom;
+
+		let events =
+		[
+			perf_sw_ids_PERF_COUNT_SW_TASK_CLOCK       ,
+			perf_sw_ids_PERF_COUNT_SW_CONTEXT_SWITCHES ,
+			perf_sw_ids_PERF_COUNT_SW_CPU_MIGRATIONS   ,
+		];
+
+		let mut threads = Vec::new();
+
+		for tid in thread_ids()
+		{
+			let mut files = Vec::with_capacity( events.len() );
+
+			for event in &events
+			{
+				match open_counter( tid, *event as u64 )
+				{
+					Ok( file ) => files.push( file ),
+
+					// The thread exited since we listed it.
+					//
+					Err( e ) if e.raw_os_error() == Some( libc::ESRCH ) => break,
+
+					Err( e ) =>
+					{
+						warn_once( e );
+						return None;
+					}
+				}
+			}
+
+			if let Ok( files ) = <[File; 3]>::try_from( files ) { threads.push( files ); }
+		}
+
+		Some( Self { threads } )
+	}
+
+
+	// Open the counters on every thread of the process. `None` if the `perf_events` feature is
+	// disabled or the counters can't be opened.
+	//
+	#[ cfg( not( all( feature = "perf_events", target_os = "linux" ) ) ) ]
+	//
+	pub fn open() -> Option<Self>
+	{
+		None
+	}
+
+
+	// Read the counters, summed over all threads.
+	//
+	pub fn read( self ) -> PerfCounters
+	{
+		let mut total = PerfCounters::default();
+
+		for [ task_clock, switches, migrations ] in self.threads
+		{
+			total.add( PerfCounters
+			{
+				task_clock_ns   : read_counter( task_clock ) ,
+				context_switches: read_counter( switches   ) ,
+				cpu_migrations  : read_counter( migrations ) ,
+			});
+		}
+
+		total
+	}
+}
+
+
+// The ids of all threads of the process.
+//
+#[ cfg( all( feature = "perf_events", target_os = "linux" ) ) ]
+//
+fn thread_ids() -> Vec<libc::pid_t>
+{
+	let tasks = match std::fs::read_dir( "/proc/self/task" )
+	{
+		Ok ( tasks ) => tasks,
+		Err( _     ) => return Vec::new(),
+	};
+
+	tasks.filter_map( |t| t.ok()?.file_name().to_str()?.parse().ok() ).collect()
+}
+
+
+// Open a software counter on thread `tid`, which also counts threads it spawns from now on. If the
+// kernel doesn't let us count kernel space, count user space only, see the module docs.
+//
+#[ cfg( all( feature = "perf_events", target_os = "linux" ) ) ]
+//
+fn open_counter( tid: libc::pid_t, event: u64 ) -> Result<File, std::io::Error>
+{
+	use std::sync::atomic::{ AtomicBool, Ordering::Relaxed };
+
+	// Once the kernel refused, don't ask again for every counter.
+	//
+	static USER_ONLY: AtomicBool = AtomicBool::new( false );
+
+	if !USER_ONLY.load( Relaxed )
+	{
+		match open_counter_with( tid, event, false )
+		{
+			Err( e ) if e.raw_os_error() == Some( libc::EACCES ) || e.raw_os_error() == Some( libc::EPERM ) =>
+			{
+				USER_ONLY.store( true, Relaxed );
+			}
+
+			res => return res,
+		}
+	}
+
+	let file = open_counter_with( tid, event, true )?;
+
+	static NOTED: std::sync::Once = std::sync::Once::new();
+
+	NOTED.call_once( || eprintln!( "perf counters only count user space, context switches and CPU migrations will read 0. See perf_event_paranoid." ) );
+
+	Ok( file )
+}
+
+
+#[ cfg( all( feature = "perf_events", target_os = "linux" ) ) ]
+//
+fn open_counter_with( tid: libc::pid_t, event: u64, user_only: bool ) -> Result<File, std::io::Error>
+{
+	use perf_event_open_sys::{ bindings::*, perf_event_open };
+	use std::os::unix::io::FromRawFd;
+
+	let mut attr = perf_event_attr
+	{
+		type_ : perf_type_id_PERF_TYPE_SOFTWARE               ,
+		size  : std::mem::size_of::<perf_event_attr>() as u32 ,
+		config: event                                         ,
+		..Default::default()
+	};
+
+	attr.set_inherit( 1 );
+
+	if user_only
+	{
+		attr.set_exclude_kernel( 1 );
+		attr.set_exclude_hv    ( 1 );
+	}
+
+	// Safety: attr is a valid perf_event_attr with the correct size. On success, we own the file
+	// descriptor that is returned.
+	//
+	let fd = unsafe { perf_event_open( &mut attr, tid, -1, -1, PERF_FLAG_FD_CLOEXEC as libc::c_ulong ) };
+
+	match fd
+	{
+		fd if fd < 0 => Err( std::io::Error::from_raw_os_error( -fd ) ),
+		fd           => Ok( unsafe { File::from_raw_fd( fd ) } ),
+	}
+}
+
+
+#[ cfg( all( feature = "perf_events", target_os = "linux" ) ) ]
+//
+fn warn_once( e: std::io::Error )
+{
+	static WARNED: std::sync::Once = std::sync::Once::new();
+
+	WARNED.call_once( || eprintln!( "Failed to open perf counters, they will not be reported: {}", e ) );
+}
+
+
+// A counter reads as a native endian u64.
+//
+fn read_counter( mut file: File ) -> u64
+{
+	let mut buf = [ 0u8; 8 ];
+
+	match file.read_exact( &mut buf )
+	{
+		Ok (_) => u64::from_ne_bytes( buf ),
+		Err(_) => 0,
+	}
+}
+
+
+
+// Software counters per iteration of a benchmark.
+//
+#[ derive( Clone, Debug, PartialEq, Serialize, Deserialize ) ]
+//
+pub struct PerfStats
+{
+	pub task_clock_ns   : f64 ,
+	pub context_switches: f64 ,
+	pub cpu_migrations  : f64 ,
+
+	// Task clock divided by wall time, ie. how many CPUs were utilized on average.
+	//
+	pub cpus_utilized: f64,
+}
+
+
+impl PerfStats
+{
+	// `elapsed` is the wall time of all the iterations, see `UsageStats::new`.
+	//
+	pub fn new( total: PerfCounters, iterations: u64, elapsed: Duration ) -> Self
+	{
+		let iterations    = iterations.max( 1 ) as f64;
+		let task_clock_ns = total.task_clock_ns as f64 / iterations;
+
+		let cpus_utilized = match elapsed.as_nanos()
+		{
+			0    => 0.0,
+			wall => total.task_clock_ns as f64 / wall as f64,
+		};
+
+		Self
+		{
+			task_clock_ns                                                ,
+			context_switches: total.context_switches as f64 / iterations ,
+			cpu_migrations  : total.cpu_migrations   as f64 / iterations ,
+			cpus_utilized                                                ,
+		}
+	}
+
+
+	pub fn from_record( record: &Record ) -> Option<Self>
+	{
+		Some( Self::new( record.perf?, record.iterations, record.elapsed ) )
+	}
+}
+
+
+
+// Prints a table of the software counters per iteration in a set of records. Records without
+// counters are skipped.
+//
+pub struct PerfReport( pub Vec<( String, PerfStats )> );
+
+
+impl PerfReport
+{
+	pub fn new( records: &[Record] ) -> Self
+	{
+		Self( records.iter().filter_map( |r| Some(( r.id.to_string(), PerfStats::from_record( r )? )) ).collect() )
+	}
+
+
+	pub fn is_empty( &self ) -> bool
+	{
+		self.0.is_empty()
+	}
+}
+
+
+impl fmt::Display for PerfReport
+{
+	fn fmt( &self, f: &mut fmt::Formatter<'_> ) -> fmt::Result
+	{
+		let width = self.0.iter().map( |(id, _)| id.len() ).max().unwrap_or( 0 );
+
+		writeln!( f, "Perf counters per iteration:" )?;
+		writeln!( f )?;
+		writeln!( f, "  {:<width$}  {:>12}  {:>6}  {:>12}  {:>12}", "", "task clock", "cpus", "ctx switches", "migrations", width = width )?;
+
+		for (id, p) in &self.0
+		{
+			let clock = format!( "{:?}", Duration::from_nanos( p.task_clock_ns as u64 ) );
+
+			writeln!
+			(
+				f, "  {:<width$}  {:>12}  {:>6.2}  {:>12.1}  {:>12.1}",
+				id, clock, p.cpus_utilized, p.context_switches, p.cpu_migrations, width = width,
+			)?;
+		}
+
+		Ok(())
+	}
+}
+
+
+
+#[ cfg( test ) ]
+//
+mod tests
+{
+	use super::*;
+
+	#[test]
+	//
+	fn per_iteration()
+	{
+		let total = PerfCounters { task_clock_ns: 4000, context_switches: 6, cpu_migrations: 2 };
+		let stats = PerfStats::new( total, 2, Duration::from_nanos( 2000 ) );
+
+		assert_eq!( 2000.0, stats.task_clock_ns    );
+		assert_eq!( 3.0   , stats.context_switches );
+		assert_eq!( 1.0   , stats.cpu_migrations   );
+		assert_eq!( 2.0   , stats.cpus_utilized    );
+	}
+}
@@ -0,0 +1,541 @@
+//! Collect the timings of benchmark runs, so we can process them after a run without depending
+//! on the output of criterion.
+//!
+use crate::{ Allocations, PerfCounters, PerfSession, ResourceUsage, Stats, TaskBalance, TaskMemory, Watch, allocations, resource_usage, take_balance, take_latencies, take_task_memory };
+use hdrhistogram::Histogram;
+use serde::{ Deserialize, Serialize };
+use std::{ fmt, sync::Mutex, time::{ Duration, Instant } };
+
+
+static RESULTS: Mutex< Vec<Record> > = Mutex::new( Vec::new() );
+
+
+// Identifies a benchmark: which workload ran on which executor, with what parameters.
+// The parameters are kept in the order they are added, which is also the display order.
+// They serialize to a map in that order.
+//
+#[ derive( Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize ) ]
+//
+pub struct BenchId
+{
+	pub group   : String ,
+	pub executor: String ,
+	pub workload: String ,
+
+	#[ serde( with = "ordered_map" ) ]
+	//
+	pub params: Vec<( String, String )>,
+}
+
+
+impl BenchId
+{
+	pub fn new( group: impl Into<String>, executor: impl Into<String>, workload: impl Into<String> ) -> Self
+	{
+		Self
+		{
+			group   : group   .into() ,
+			executor: executor.into() ,
+			workload: workload.into() ,
+			params  : Vec::new()      ,
+		}
+	}
+
+
+	// Add a parameter.
+	//
+	pub fn param( mut self, name: impl Into<String>, value: impl fmt::Display ) -> Self
+	{
+		self.params.push( ( name.into(), value.to_string() ) );
+		self
+	}
+
+
+	// Get the value of a parameter.
+	//
+	pub fn get( &self, name: &str ) -> Option<&str>
+	{
+		self.params.iter().find( |(n, _)| n == name ).map( |(_, v)| v.as_str() )
+	}
+}
+
+
+impl fmt::Display for BenchId
+{
+	fn fmt( &self, f: &mut fmt::Formatter<'_> ) -> fmt::Result
+	{
+		write!( f, "{} {}", self.executor, self.workload )?;
+
+		for (_, value) in &self.params
+		{
+			write!( f, " {}", value )?;
+		}
+
+		Ok(())
+	}
+}
+
+
+
+// The samples of one benchmark. Each sample is the mean time of one iteration in a batch
+// of iterations.
+//
+#[ derive( Clone, Debug ) ]
+//
+pub struct Record
+{
+	pub id     : BenchId       ,
+	pub samples: Vec<Duration> ,
+
+	// The latencies recorded by the workload, if it records any. See the `latency` module.
+	//
+	pub latency: Option< Histogram<u64> >,
+
+	// The iterations run by `timed`, their total time and the allocations they made, if they are
+	// counted. See the `alloc_counter` module.
+	//
+	pub iterations : u64                 ,
+	pub elapsed    : Duration            ,
+	pub allocations: Option<Allocations> ,
+
+	// The resource usage of the process during those iterations. See the `rusage` module.
+	//
+	pub usage: Option<ResourceUsage>,
+
+	// Software counters of those iterations, with the `perf_events` feature. See the `perf_counters` module.
+	//
+	pub perf: Option<PerfCounters>,
+
+	// The memory footprint of idle tasks, for the `IdleTasks` workload. See the `task_memory` module.
+	//
+	pub memory: Option<TaskMemory>,
+
+	// The tasks run per thread, for the `WorkStealing` workload. See the `work_stealing` module.
+	//
+	pub balance: Option<TaskBalance>,
+
+	// What was recorded with every sample, so `keep_last` can drop it together with the sample.
+	//
+	batches: Vec<Batch>,
+}
+
+
+impl Record
+{
+	pub fn new( id: BenchId, samples: Vec<Duration> ) -> Self
+	{
+		let batches = vec![ Batch::default(); samples.len() ];
+
+		Self { id, samples, latency: None, iterations: 0, elapsed: Duration::default(), allocations: None, usage: None, perf: None, memory: None, balance: None, batches }
+	}
+
+
+	pub fn mean( &self ) -> Duration
+	{
+		if self.samples.is_empty() { return Duration::default() }
+
+		self.samples.iter().sum::<Duration>() / self.samples.len() as u32
+	}
+
+
+	pub fn stats( &self ) -> Stats
+	{
+		Stats::new( &self.samples )
+	}
+
+
+	// Add `batch` to the last sample and to the totals.
+	//
+	fn add( &mut self, batch: Batch )
+	{
+		let mut total = self.take_total();
+
+		total.add( &batch );
+		self.batches.last_mut().expect( "a record has a sample" ).add( &batch );
+
+		self.set_total( total );
+	}
+
+
+	// Sum up the batches of the samples that are left.
+	//
+	fn recount( &mut self )
+	{
+		let mut total = Batch::default();
+
+		for batch in &self.batches { total.add( batch ); }
+
+		self.set_total( total );
+	}
+
+
+	fn take_total( &mut self ) -> Batch
+	{
+		Batch
+		{
+			iterations : self.iterations     ,
+			elapsed    : self.elapsed        ,
+			allocations: self.allocations    ,
+			usage      : self.usage          ,
+			perf       : self.perf           ,
+			latency    : self.latency.take() ,
+			balance    : self.balance.take() ,
+		}
+	}
+
+
+	fn set_total( &mut self, total: Batch )
+	{
+		self.iterations  = total.iterations  ;
+		self.elapsed     = total.elapsed     ;
+		self.allocations = total.allocations ;
+		self.usage       = total.usage       ;
+		self.perf        = total.perf        ;
+		self.latency     = total.latency     ;
+		self.balance     = total.balance     ;
+	}
+}
+
+
+
+// What a batch of iterations recorded besides it's time.
+//
+#[ derive( Clone, Debug, Default ) ]
+//
+struct Batch
+{
+	iterations : u64                      ,
+	elapsed    : Duration                 ,
+	allocations: Option<Allocations>      ,
+	usage      : Option<ResourceUsage>    ,
+	perf       : Option<PerfCounters>     ,
+	latency    : Option< Histogram<u64> > ,
+	balance    : Option<TaskBalance>      ,
+}
+
+
+impl Batch
+{
+	fn add( &mut self, other: &Batch )
+	{
+		self.iterations += other.iterations;
+		self.elapsed    += other.elapsed;
+
+		if let Some( allocs ) = other.allocations
+		{
+			let total = self.allocations.get_or_insert_with( Allocations::default );
+
+			total.count += allocs.count;
+			total.bytes += allocs.bytes;
+		}
+
+		if let Some( usage ) = other.usage
+		{
+			self.usage.get_or_insert_with( ResourceUsage::default ).add( usage );
+		}
+
+		if let Some( perf ) = other.perf
+		{
+			self.perf.get_or_insert_with( PerfCounters::default ).add( perf );
+		}
+
+		if let Some( latencies ) = &other.latency
+		{
+			match &mut self.latency
+			{
+				Some( histogram ) => histogram.add( latencies ).expect( "histograms grow to fit" ),
+				None              => self.latency = Some( latencies.clone() ),
+			}
+		}
+
+		if let Some( balance ) = &other.balance
+		{
+			self.balance.get_or_insert_with( TaskBalance::default ).merge( balance );
+		}
+	}
+}
+
+
+
+// Run `f` `iters` times and record the mean time per iteration as a sample for `id`, as well as
+// the latencies, memory and tasks per thread recorded by `f`, the allocations it made, the resources
+// it used and the perf counters. Returns the total time, so it can be used with `Bencher::iter_custom`.
+// If an iteration hangs, the watchdog aborts the process, see the `watchdog` module.
+//
+pub fn timed( id: &BenchId, iters: u64, mut f: impl FnMut() ) -> Duration
+{
+	// Drop latencies and task counts recorded outside of `timed`, eg. by a warm up of the runner. Task
+	// memory is kept, as the first iteration is the one that grows the process. Criterion warms up by
+	// calling `timed`, `keep_last` drops what those batches recorded.
+	//
+	take_latencies();
+	take_balance();
+
+	// Opening the perf counters and arming the watch allocate, so the allocations are counted
+	// right around the loop, like the time.
+	//
+	let watch        = Watch::new( id );
+	let perf         = PerfSession::open();
+	let usage_before = resource_usage();
+	let before       = allocations();
+	let start        = Instant::now();
+
+	for _ in 0..iters
+	{
+		if let Some( watch ) = &watch { watch.iteration(); }
+
+		f();
+	}
+
+	let elapsed     = start.elapsed();
+	let after       = allocations();
+	let usage_after = resource_usage();
+	let perf        = perf.map( PerfSession::read );
+
+	drop( watch );
+
+	// `iters` can be more than fits in the u32 that `Duration` divides by.
+	//
+	record( id, Duration::from_nanos( ( elapsed.as_nanos() / u128::from( iters.max( 1 ) ) ) as u64 ) );
+
+	record_iterations
+	(
+		id,
+		iters,
+		elapsed,
+		after.zip( before ).map( |(a, b)| a - b ),
+		usage_after.zip( usage_before ).map( |(a, b)| a - b ),
+	);
+
+	if let Some( perf ) = perf
+	{
+		record_perf( id, perf );
+	}
+
+	if let Some( latencies ) = take_latencies()
+	{
+		record_latency( id, &latencies );
+	}
+
+	if let Some( memory ) = take_task_memory()
+	{
+		record_memory( id, memory );
+	}
+
+	if let Some( balance ) = take_balance()
+	{
+		record_task_balance( id, &balance );
+	}
+
+	elapsed
+}
+
+
+// Add a sample for `id`.
+//
+pub fn record( id: &BenchId, sample: Duration )
+{
+	let mut results = RESULTS.lock().expect( "lock results" );
+
+	match results.iter_mut().find( |r| &r.id == id )
+	{
+		Some( r ) =>
+		{
+			r.samples.push( sample );
+			r.batches.push( Batch::default() );
+		}
+
+		None => results.push( Record::new( id.clone(), vec![ sample ] ) ),
+	}
+}
+
+
+// Add `batch` to the last sample of `id`.
+//
+fn record_batch( id: &BenchId, batch: Batch, expect: &str )
+{
+	let mut results = RESULTS.lock().expect( "lock results" );
+
+	results.iter_mut().find( |r| &r.id == id ).expect( expect ).add( batch );
+}
+
+
+// Add iterations, their total time, the allocations they made and the resources they used to `id`.
+// The benchmark must already have a sample.
+//
+pub fn record_iterations( id: &BenchId, iters: u64, elapsed: Duration, allocs: Option<Allocations>, usage: Option<ResourceUsage> )
+{
+	let batch = Batch { iterations: iters, elapsed, allocations: allocs, usage, ..Batch::default() };
+
+	record_batch( id, batch, "record iterations after a sample" );
+}
+
+
+// Add latencies to the histogram of `id`. The benchmark must already have a sample.
+//
+pub fn record_latency( id: &BenchId, latencies: &Histogram<u64> )
+{
+	let batch = Batch { latency: Some( latencies.clone() ), ..Batch::default() };
+
+	record_batch( id, batch, "record latencies after a sample" );
+}
+
+
+// Only keep the last `n` samples for `id`. Criterion also calls the routine during warm up,
+// so after a benchmark is done, the last `sample_size` samples are the real ones. The iterations,
+// allocations, resource usage, perf counters, latencies and task balance recorded with the dropped
+// samples are dropped as well. Task memory is kept, see `timed`.
+//
+pub fn keep_last( id: &BenchId, n: usize )
+{
+	let mut results = RESULTS.lock().expect( "lock results" );
+
+	if let Some( r ) = results.iter_mut().find( |r| &r.id == id )
+	{
+		let drop = r.samples.len().saturating_sub( n );
+
+		r.samples.drain( ..drop );
+		r.batches.drain( ..drop );
+
+		r.recount();
+	}
+}
+
+
+// Add perf counters to `id`. The benchmark must already have a sample.
+//
+pub fn record_perf( id: &BenchId, perf: PerfCounters )
+{
+	let batch = Batch { perf: Some( perf ), ..Batch::default() };
+
+	record_batch( id, batch, "record perf counters after a sample" );
+}
+
+
+// Merge the memory footprint of idle tasks into `id`. The benchmark must already have a sample.
+//
+pub fn record_memory( id: &BenchId, memory: TaskMemory )
+{
+	let mut results = RESULTS.lock().expect( "lock results" );
+	let     record  = results.iter_mut().find( |r| &r.id == id ).expect( "record memory after a sample" );
+
+	match &mut record.memory
+	{
+		Some( m ) => m.merge( memory ),
+		None      => record.memory = Some( memory ),
+	}
+}
+
+
+// Add the tasks run per thread to `id`. The benchmark must already have a sample.
+//
+pub fn record_task_balance( id: &BenchId, balance: &TaskBalance )
+{
+	let batch = Batch { balance: Some( balance.clone() ), ..Batch::default() };
+
+	record_batch( id, batch, "record task balance after a sample" );
+}
+
+
+// A copy of all the results recorded so far.
+//
+pub fn results() -> Vec<Record>
+{
+	RESULTS.lock().expect( "lock results" ).clone()
+}
+
+
+
+// Serialize a list of pairs as a map, keeping the order.
+//
+mod ordered_map
+{
+	use serde::{ Deserializer, Serializer, de::{ MapAccess, Visitor }, ser::SerializeMap };
+	use std::fmt;
+
+	pub fn serialize<S: Serializer>( pairs: &[( String, String )], serializer: S ) -> Result<S::Ok, S::Error>
+	{
+		let mut map = serializer.serialize_map( Some( pairs.len() ) )?;
+
+		for (k, v) in pairs { map.serialize_entry( k, v )?; }
+
+		map.end()
+	}
+
+
+	pub fn deserialize<'de, D: Deserializer<'de>>( deserializer: D ) -> Result<Vec<( String, String )>, D::Error>
+	{
+		deserializer.deserialize_map( PairsVisitor )
+	}
+
+
+	struct PairsVisitor;
+
+	impl<'de> Visitor<'de> for PairsVisitor
+	{
+		type Value = Vec<( String, String )>;
+
+		fn expecting( &self, f: &mut fmt::Formatter<'_> ) -> fmt::Result
+		{
+			write!( f, "a map of parameter names to values" )
+		}
+
+		fn visit_map<A: MapAccess<'de>>( self, mut access: A ) -> Result<Self::Value, A::Error>
+		{
+			let mut pairs = Vec::new();
+
+			while let Some( pair ) = access.next_entry()? { pairs.push( pair ); }
+
+			Ok( pairs )
+		}
+	}
+}
+
+
+
+#[ cfg( test ) ]
+//
+mod tests
+{
+	use super::*;
+
+	#[test]
+	//
+	fn display()
+	{
+		let id = BenchId::new( "Ring", "TokioTp", "HandleRing" ).param( "forward", "await" ).param( "nodes", 10 );
+
+		assert_eq!( "TokioTp HandleRing await 10", id.to_string() );
+		assert_eq!( Some( "10" ), id.get( "nodes" ) );
+		assert_eq!( None        , id.get( "laps"  ) );
+	}
+
+
+	#[test]
+	//
+	fn keep_last_samples()
+	{
+		let id = BenchId::new( "results tests", "none", "keep_last" );
+
+		for i in 0..5
+		{
+			record( &id, Duration::from_millis( i ) );
+			record_iterations( &id, i + 1, Duration::from_millis( i * ( i + 1 ) ), Some( Allocations { count: i, bytes: 8 * i } ), None );
+			record_task_balance( &id, &TaskBalance { tasks_per_thread: vec![ i ] } );
+		}
+
+		keep_last( &id, 2 );
+
+		let record = results().into_iter().find( |r| r.id == id ).expect( "find record" );
+
+		assert_eq!( vec![ Duration::from_millis( 3 ), Duration::from_millis( 4 ) ], record.samples );
+		assert_eq!( Duration::from_micros( 3500 ), record.mean() );
+
+		// Only what the last 2 batches recorded is left.
+		//
+		assert_eq!( 9                                                  , record.iterations  );
+		assert_eq!( Duration::from_millis( 32 )                        , record.elapsed     );
+		assert_eq!( Some( Allocations { count: 7, bytes: 56 } )        , record.allocations );
+		assert_eq!( Some( TaskBalance { tasks_per_thread: vec![ 7 ] } ), record.balance     );
+	}
+}
@@ -0,0 +1,355 @@
+//! The nodes shared by all rings. Every ring runs the same nodes, it only decides how they and the
+//! tasks that forward messages get spawned, by implementing [`RingSpawn`] with the API it benchmarks.
+//!
+//! Each node starts by sending a 1 to the next node, and forwards every message it receives, incremented,
+//! until it's own message has come back after the last lap. The last node to get it's message back
+//! closes the ring and every node counts down when it stops, the last one signals the ring that the run
+//! is over. That way no node still holds a clone of the executor when `run` returns.
+//!
+use futures::{ SinkExt, StreamExt, channel::mpsc };
+use std::{ future::Future, sync::{ Arc, Mutex, atomic::{ AtomicUsize, Ordering } } };
+use log::*;
+use crate::{ Forward, Progress, RingCheck, RingConfig, RingTask, record_latencies };
+
+
+// How a ring spawns it's nodes and the tasks that forward messages.
+//
+pub trait RingSpawn: Clone + 'static
+{
+	// Resolves when a task spawned with `spawn_awaited` is done.
+	//
+	type Handle: Future<Output = ()>;
+
+	// Spawn a node, it keeps a clone of `self` to forward messages.
+	//
+	fn spawn_node<M: Message>( &self, node: Node<M>, done: mpsc::Sender<()> );
+
+	// Spawn a task to forward a message without waiting for it, `Forward::Detach`.
+	//
+	fn detach( &self, task: impl Future<Output = ()> + Send + 'static );
+
+	// Spawn a task to forward a message and wait for it, `Forward::Await`.
+	//
+	fn spawn_awaited( &self, task: impl Future<Output = ()> + Send + 'static ) -> Self::Handle;
+}
+
+
+// What goes round the ring: the number of nodes that forwarded it.
+//
+pub trait Message: Copy + Send + 'static
+{
+	// Whether the nodes record how long every hop took.
+	//
+	const TIMED: bool;
+
+	// The message a node forwards now.
+	//
+	fn forward( count: usize ) -> Self;
+
+	fn count( self ) -> usize;
+
+	// The time since the message was forwarded in nanoseconds. Only called if `TIMED`.
+	//
+	fn hop( self ) -> u64;
+}
+
+
+impl Message for usize
+{
+	const TIMED: bool = false;
+
+	fn forward( count: usize ) -> Self { count }
+	fn count  ( self         ) -> usize { self  }
+	fn hop    ( self         ) -> u64   { 0     }
+}
+
+
+
+pub struct Ring<M: Message = usize>
+{
+	nodes: Option< Vec< Node<M> > > ,
+	state: Arc<RingState>           ,
+}
+
+
+// What the nodes of a ring share.
+//
+struct RingState
+{
+	n        : usize                    ,
+	end      : usize                    ,
+	forward  : Forward                  ,
+	check    : Option< Arc<RingCheck> > ,
+	progress : Option< Arc<Progress> >  ,
+	done     : AtomicUsize              ,
+	latencies: Mutex< Vec<Vec<u64>> >   ,
+}
+
+
+impl<M: Message> Ring<M>
+{
+	// Create channels between all the nodes.
+	//
+	pub fn with_config( config: RingConfig ) -> Self
+	{
+		let RingConfig { nodes: n, capacity, laps, forward, .. } = config;
+
+		assert!( n        > 1 );
+		assert!( laps     > 0 );
+		assert!( capacity > 0 );
+
+		let end = config.end();
+
+		let state = Arc::new( RingState
+		{
+			n, end, forward,
+			check    : RingCheck::new( &config )                                         ,
+			progress : Progress::new( &config )                                          ,
+			done     : AtomicUsize::new( 0 )                                             ,
+			latencies: Mutex::new( Vec::with_capacity( if M::TIMED { n } else { 0 } ) ) ,
+		});
+
+		let mut nodes = Vec::with_capacity( n );
+
+		// Every node receives every message on every lap, so a timed node measures `end` hops.
+		//
+		let node = |id, tx, rx| Node
+		{
+			id, tx, rx,
+			hops : Vec::with_capacity( if M::TIMED { end } else { 0 } ),
+			task : None,
+			state: state.clone(),
+		};
+
+		// The channel always has one slot per sender, so this is a bounded
+		// channel of size channel_size + 1. See `RingConfig::new` for the default.
+		//
+		let channel_size = capacity;
+
+		// The connection between the last and the first node.
+		//
+		let (last_tx, last_rx) = mpsc::channel( channel_size );
+
+		// The first node
+		//
+		let (tx, mut next_rx) = mpsc::channel( channel_size );
+
+		nodes.push( node( 1, tx, last_rx ) );
+
+
+		// All but first and last.
+		// Note that 1..1 does not do anything, so it works if n==2.
+		//
+		for id in 2..n
+		{
+			let (tx, rx) = mpsc::channel( channel_size );
+
+			nodes.push( node( id, tx, next_rx ) );
+
+			next_rx = rx;
+		}
+
+
+		// The last node
+		//
+		nodes.push( node( n, last_tx, next_rx ) );
+
+		Self
+		{
+			nodes: Some( nodes ),
+			state,
+		}
+	}
+
+
+	// Run the benchmark.
+	//
+	pub async fn run( &mut self, exec: impl RingSpawn )
+	{
+		debug!( "Ring: start" );
+
+		let (done_tx, mut done_rx) = mpsc::channel(0);
+
+		for mut node in self.nodes.take().expect( "a ring only runs once" ).into_iter()
+		{
+			node.task = self.state.check.as_ref().map( RingCheck::task );
+
+			exec.spawn_node( node, done_tx.clone() );
+		}
+
+		let res = done_rx.next().await;
+
+		debug_assert!( res.is_some() );
+		drop( exec );
+
+		if M::TIMED { self.record(); }
+
+		if let Some( check ) = &self.state.check { check.finished().await; }
+
+		debug!( "Ring: end" );
+	}
+
+
+	// All nodes are done, so nothing is running anymore that could be slowed down by recording.
+	//
+	fn record( &self )
+	{
+		let latencies = std::mem::take( &mut *self.state.latencies.lock().expect( "lock latencies" ) );
+
+		record_latencies( latencies.into_iter().flatten() );
+	}
+}
+
+
+
+// A node of a ring, see the module documentation.
+//
+pub struct Node<M: Message>
+{
+	id   : usize             ,
+	tx   : mpsc::Sender  <M> ,
+	rx   : mpsc::Receiver<M> ,
+	hops : Vec<u64>          ,
+	task : Option<RingTask>  ,
+	state: Arc<RingState>    ,
+}
+
+
+impl<M: Message> Node<M>
+{
+	pub async fn run( mut self, exec: impl RingSpawn, mut done_tx: mpsc::Sender<()> )
+	{
+		debug!( "Node {}: run", self.id );
+
+		self.forward( 1, &exec ).await;
+
+		// Two ways out of this loop:
+		// - if we are the last node to finish, we break and then close our channel.
+		// - if we aren't the last, we will end the loop when the channel is closed and returns None.
+		//
+		while let Some( msg ) = self.rx.next().await
+		{
+			if M::TIMED { self.hops.push( msg.hop() ); }
+
+			let msg = msg.count();
+
+			debug_assert!( msg <= self.state.end );
+
+			if let Some( progress ) = &self.state.progress { progress.seen( self.id, msg ); }
+
+			if let Some( check ) = &self.state.check { check.receive( self.id, msg ); }
+
+			// When our message comes back after the last lap, it should be counted by everyone
+			// on every lap, so it should be n * laps. Otherwise it continues on it's way.
+			//
+			if msg == self.state.end
+			{
+				trace!( "Node {}: received our own message back", self.id );
+
+				// Store the fact that we are done.
+				//
+				let old = self.state.done.fetch_add( 1, Ordering::SeqCst );
+
+				// If we are the last one.
+				//
+				if old+1 == self.state.n
+				{
+					trace!( "Node {}: all done", self.id );
+
+					break;
+				}
+
+				// else continue passing on messages for the other nodes.
+				//
+				continue;
+			}
+
+			trace!( "Node {}: forwarding a message", self.id );
+
+			self.forward( msg + 1, &exec ).await;
+		}
+
+		trace!( "Node {}: close our sender", self.id );
+
+		// Allow nodes that where still passing on messages to detect that we are done.
+		//
+		self.tx.close().await.expect( "close channel" );
+
+		drop( exec );
+
+		if M::TIMED
+		{
+			let hops = std::mem::take( &mut self.hops );
+
+			self.state.latencies.lock().expect( "lock latencies" ).push( hops );
+		}
+
+		// Now count down to make sure everyone has dropped the executor before signaling done_tx.
+		// This is important because it's a benchmark and we don't want code to keep running after
+		// each iteration and also because tokio runtime panics if it get's dropped from async context,
+		// so we absolutely need to make sure that these are dropped before the block_on returns.
+		//
+		let old = self.state.done.fetch_sub( 1, Ordering::SeqCst );
+
+		// We are the last one to drop our exec.
+		//
+		if old - 1 == 0
+		{
+			done_tx.send(()).await.expect( "Send DONE" );
+		}
+
+		debug!( "Node {}: run END", self.id );
+	}
+
+
+	// Pass a message on to the next node, according to the forwarding mode of the ring.
+	//
+	async fn forward( &mut self, count: usize, exec: &impl RingSpawn )
+	{
+		let msg = M::forward( count );
+
+		match self.state.forward
+		{
+			Forward::Inline =>
+			{
+				self.tx.send( msg ).await.expect( "Node: forward message" );
+			}
+
+			Forward::Detach =>
+			{
+				let mut tx    = self.tx.clone();
+				let     guard = self.state.check.as_ref().map( RingCheck::task );
+
+				exec.detach( async move { let _guard = guard; tx.send( msg ).await.expect( "Node: forward message" ); } );
+			}
+
+			Forward::Await =>
+			{
+				let mut tx    = self.tx.clone();
+				let     guard = self.state.check.as_ref().map( RingCheck::task );
+
+				exec.spawn_awaited( async move { let _guard = guard; tx.send( msg ).await.expect( "Node: forward message" ); } ).await;
+			}
+		}
+	}
+}
+
+
+
+#[ cfg( test ) ]
+//
+mod tests
+{
+	use super::*;
+
+	#[test]
+	//
+	fn off_by_one_or_not()
+	{
+		let ring2 = Ring::<usize>::with_config( RingConfig::new( 2, Forward::Detach ) );
+		assert_eq!( 2, ring2.nodes.unwrap().len() );
+
+		let ring2 = Ring::<usize>::with_config( RingConfig::new( 3, Forward::Detach ) );
+		assert_eq!( 3, ring2.nodes.unwrap().len() );
+	}
+}
@@ -0,0 +1,126 @@
+//! The parameters of a ring, shared by all ring implementations.
+//!
+use crate::Forward;
+use std::fmt;
+
+
+// Describes a ring. Create it with `new` and change the defaults with the setters.
+//
+#[ derive( Copy, Clone, Debug, PartialEq, Eq, Hash ) ]
+//
+pub struct RingConfig
+{
+	// The number of nodes in the ring, must be at least 2.
+	//
+	pub nodes: usize,
+
+	// The capacity of the channels between nodes. The channel always has one slot per sender,
+	// so the effective capacity is one more. Must be at least 1: the slot of a sender only frees
+	// up when the next node takes the message, so with 0, once every node waits on it's own send
+	// (`Forward::Inline` and `Forward::Await`), none of them receives and the ring deadlocks.
+	// Detached sends don't wait, every task has it's own clone of the sender and thus it's own slot.
+	//
+	pub capacity: usize,
+
+	// How many times each message travels round the ring before it's node is done.
+	//
+	pub laps: usize,
+
+	pub forward: Forward,
+
+	// Check that every message arrives in order and completes it's laps, and that no task
+	// outlives the run. See the `verify` module.
+	//
+	pub verify: bool,
+}
+
+
+impl RingConfig
+{
+	// A ring with a channel capacity of 1 and a single lap.
+	//
+	// I choose a low capacity in the hopes that some tasks will block, creating a bit more
+	// work for the executor and a more realistic benchmark.
+	//
+	pub fn new( nodes: usize, forward: Forward ) -> Self
+	{
+		Self { nodes, capacity: 1, laps: 1, forward, verify: false }
+	}
+
+
+	pub fn capacity( mut self, capacity: usize ) -> Self
+	{
+		self.capacity = capacity;
+		self
+	}
+
+
+	pub fn laps( mut self, laps: usize ) -> Self
+	{
+		self.laps = laps;
+		self
+	}
+
+
+	pub fn verify( mut self, verify: bool ) -> Self
+	{
+		self.verify = verify;
+		self
+	}
+
+
+	// The value of a message when it comes back to the node it originates from after the last lap.
+	// Every node increments it, so it's been counted by every node `laps` times.
+	//
+	pub fn end( &self ) -> usize
+	{
+		self.nodes * self.laps
+	}
+}
+
+
+impl fmt::Display for RingConfig
+{
+	fn fmt( &self, f: &mut fmt::Formatter<'_> ) -> fmt::Result
+	{
+		write!( f, "{} nodes, capacity {}, {} laps, {}", self.nodes, self.capacity, self.laps, self.forward )?;
+
+		if self.verify { write!( f, ", verified" )?; }
+
+		Ok(())
+	}
+}
+
+
+
+#[ cfg( test ) ]
+//
+mod tests
+{
+	use super::*;
+	use crate::{ BoundedRing, LocalRing };
+	use futures::executor::LocalPool;
+
+	// Rings should terminate with several laps and with larger channels.
+	//
+	#[test]
+	//
+	fn laps_and_capacity()
+	{
+		let mut pool    = LocalPool::new();
+		let     spawner = pool.spawner();
+
+		for forward in Forward::ALL.iter()
+		{
+			let config = RingConfig::new( 10, *forward ).laps( 3 ).capacity( 4 );
+
+			assert_eq!( 30, config.end() );
+
+			let mut ring = LocalRing::with_config( config );
+			pool.run_until( ring.run( spawner.clone() ) );
+
+			let mut ring = BoundedRing::with_config( config );
+			pool.run_until( ring.run_local( spawner.clone() ) );
+		}
+	}
+}
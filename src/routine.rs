@@ -0,0 +1,618 @@
+//! Create the routine that runs one iteration of a workload on an executor, so the ring
+//! benchmark and the runner binary run exactly the same code.
+//!
+use crate::*;
+use async_executors::{ AsyncStd, LocalSpawnHandle, SpawnHandle, TokioCt, TokioTp };
+use futures::{ executor::{ LocalPool, LocalSpawner, ThreadPool, block_on }, task::{ LocalSpawn, Spawn } };
+use std::{ convert::TryFrom, future::Future, time::Duration };
+use tokio::{ runtime::{ Builder, Handle, Runtime }, task::LocalSet };
+
+
+// One iteration of a benchmark.
+//
+pub type Routine = Box< dyn FnMut() >;
+
+
+// All supported combinations of executor and workload, in the order they are benchmarked.
+// The native rings are only supported on the executor they are written for.
+//
+pub const ROUTINES: &[( &str, &str )] =
+&[
+	( "futures::executor::LocalPool"                        , "LocalPoolNativeRing"  ),
+	( "LocalPool"                                           , "LocalRing"            ),
+	( "LocalPool"                                           , "LocalHandleRing"      ),
+	( "MiniExec"                                            , "LocalRing"            ),
+	( "MiniExec"                                            , "BoundedRing"          ),
+	( "tokio::runtime::Runtime (basic_scheduler + LocalSet)", "TokioCtNativeRing"    ),
+	( "TokioCt"                                             , "LocalRing"            ),
+	( "TokioCt"                                             , "LocalHandleRing"      ),
+	( "async_executor::LocalExecutor"                       , "SmolLocalNativeRing"  ),
+	( "SmolLocal"                                           , "LocalRing"            ),
+	( "futures::executor::ThreadPool"                       , "ThreadPoolNativeRing" ),
+	( "ThreadPool"                                          , "BoundedRing"          ),
+	( "ThreadPool"                                          , "HandleRing"           ),
+	( "tokio::runtime::Runtime"                             , "TokioTpNativeRing"    ),
+	( "TokioTp"                                             , "BoundedRing"          ),
+	( "TokioTp"                                             , "HandleRing"           ),
+	( "async_std::task"                                     , "AsyncStdNativeRing"   ),
+	( "AsyncStd"                                            , "BoundedRing"          ),
+	( "AsyncStd"                                            , "HandleRing"           ),
+	( "async_executor::Executor"                            , "SmolNativeRing"       ),
+	( "SmolTp"                                              , "BoundedRing"          ),
+];
+
+
+// Combinations that record latencies besides the run time, see the `latency` module. They are
+// left out of `ROUTINES`, so the default scenario only compares throughput.
+//
+// The spawn and wake latency workloads ignore the ring parameters, except nodes, which is the
+// number of tasks they spawn or wakes they measure per iteration. For `Fairness`, nodes is the
+// number of short tasks, and there is a heavy task for every worker thread, so one for the
+// single threaded executors.
+//
+pub const LATENCY_ROUTINES: &[( &str, &str )] =
+&[
+	( "LocalPool" , "LatencyRing"             ),
+	( "LocalPool" , "SpawnLocalLatency"       ),
+	( "LocalPool" , "LocalSpawnHandleLatency" ),
+	( "LocalPool" , "WakeLatency"             ),
+	( "LocalPool" , "RemoteWakeLatency"       ),
+	( "LocalPool" , "Fairness"                ),
+	( "MiniExec"  , "LatencyRing"             ),
+	( "MiniExec"  , "SpawnLocalLatency"       ),
+	( "MiniExec"  , "WakeLatency"             ),
+	( "MiniExec"  , "RemoteWakeLatency"       ),
+	( "MiniExec"  , "Fairness"                ),
+	( "TokioCt"   , "LatencyRing"             ),
+	( "TokioCt"   , "SpawnLocalLatency"       ),
+	( "TokioCt"   , "LocalSpawnHandleLatency" ),
+	( "TokioCt"   , "WakeLatency"             ),
+	( "TokioCt"   , "RemoteWakeLatency"       ),
+	( "TokioCt"   , "Fairness"                ),
+	( "SmolLocal" , "LatencyRing"             ),
+	( "SmolLocal" , "SpawnLocalLatency"       ),
+	( "SmolLocal" , "WakeLatency"             ),
+	( "SmolLocal" , "RemoteWakeLatency"       ),
+	( "SmolLocal" , "Fairness"                ),
+	( "ThreadPool", "LatencyRing"             ),
+	( "ThreadPool", "SpawnLatency"            ),
+	( "ThreadPool", "SpawnHandleLatency"      ),
+	( "ThreadPool", "WakeLatency"             ),
+	( "ThreadPool", "RemoteWakeLatency"       ),
+	( "ThreadPool", "Fairness"                ),
+	( "TokioTp"   , "LatencyRing"             ),
+	( "TokioTp"   , "SpawnLatency"            ),
+	( "TokioTp"   , "SpawnHandleLatency"      ),
+	( "TokioTp"   , "WakeLatency"             ),
+	( "TokioTp"   , "RemoteWakeLatency"       ),
+	( "TokioTp"   , "Fairness"                ),
+	( "AsyncStd"  , "LatencyRing"             ),
+	( "AsyncStd"  , "SpawnLatency"            ),
+	( "AsyncStd"  , "SpawnHandleLatency"      ),
+	( "AsyncStd"  , "WakeLatency"             ),
+	( "AsyncStd"  , "RemoteWakeLatency"       ),
+	( "AsyncStd"  , "Fairness"                ),
+	( "SmolTp"    , "LatencyRing"             ),
+	( "SmolTp"    , "SpawnLatency"            ),
+	( "SmolTp"    , "WakeLatency"             ),
+	( "SmolTp"    , "RemoteWakeLatency"       ),
+	( "SmolTp"    , "Fairness"                ),
+];
+
+
+// Combinations that record the memory footprint of idle tasks, see the `task_memory` module. Nodes
+// is the number of tasks spawned per iteration.
+//
+pub const MEMORY_ROUTINES: &[( &str, &str )] =
+&[
+	( "LocalPool" , "IdleTasks" ),
+	( "MiniExec"  , "IdleTasks" ),
+	( "TokioCt"   , "IdleTasks" ),
+	( "SmolLocal" , "IdleTasks" ),
+	( "ThreadPool", "IdleTasks" ),
+	( "TokioTp"   , "IdleTasks" ),
+	( "AsyncStd"  , "IdleTasks" ),
+	( "SmolTp"    , "IdleTasks" ),
+];
+
+
+// Combinations that record how many tasks every thread ran, see the `work_stealing` module. Only
+// the multithreaded executors, nodes is the number of tasks spawned per iteration.
+//
+pub const BALANCE_ROUTINES: &[( &str, &str )] =
+&[
+	( "ThreadPool", "WorkStealing" ),
+	( "TokioTp"   , "WorkStealing" ),
+	( "AsyncStd"  , "WorkStealing" ),
+	( "SmolTp"    , "WorkStealing" ),
+];
+
+
+// Whether the default matrix runs `workload` in `forward` mode. The handle rings only spawn through
+// `SpawnHandle` and `LocalSpawnHandle` with `Forward::Await`. In the other modes they run the same code
+// as `BoundedRing` and `LocalRing`, so they would only be duplicates.
+//
+pub fn in_default_matrix( workload: &str, forward: Forward ) -> bool
+{
+	match workload
+	{
+		"HandleRing" | "LocalHandleRing" => forward == Forward::Await,
+		_                                => true,
+	}
+}
+
+
+// Whether `routine` supports the combination.
+//
+pub fn supported( executor: &str, workload: &str ) -> bool
+{
+	ROUTINES.iter().chain( LATENCY_ROUTINES ).chain( MEMORY_ROUTINES ).chain( BALANCE_ROUTINES ).any( |r| *r == ( executor, workload ) )
+}
+
+
+// Whether `executor` can run with `threads` worker threads in this process. The async-std executors
+// share a global runtime that keeps the number of threads it started with, so once it runs, they
+// only fit that number. Skip the others, or `Fairness` and `WorkStealing` would count the wrong
+// number of workers. See `start_async_std`.
+//
+pub fn threads_fit( executor: &str, threads: Option<usize> ) -> bool
+{
+	match ( executor, threads )
+	{
+		( "AsyncStd", Some( threads ) ) | ( "async_std::task", Some( threads ) ) =>
+		{
+			async_std_started().iter().all( |started| *started == threads )
+		}
+
+		_ => true,
+	}
+}
+
+
+// Create the executor and return a routine that runs one iteration of `workload` on it.
+// The executor lives as long as the routine.
+//
+// `threads` sets the number of worker threads of the multithreaded executors and is ignored by
+// the single threaded ones. When `None`, the executor uses it's default.
+//
+// Returns `None` if the combination is not `supported`, or the threads don't fit, see `threads_fit`.
+//
+pub fn routine( executor: &str, workload: &str, config: RingConfig, threads: Option<usize>, pinning: &Pinning ) -> Option<Routine>
+{
+	if !supported( executor, workload ) || !threads_fit( executor, threads ) { return None; }
+
+	// The threads `WorkStealing` balances it's tasks over, and the number of heavy tasks of
+	// `Fairness`, one per worker.
+	//
+	let workers = workers( executor, threads );
+
+	match executor
+	{
+		// MiniExec is a minimal executor as a baseline for the single threaded ones.
+		//
+		"LocalPool"  => local_handle_workload( LocalPool::new()               , workload, config, workers ),
+		"MiniExec"   => local_workload       ( MiniExec::new()                , workload, config, workers ),
+		"TokioCt"    => local_handle_workload( tokio_ct()                     , workload, config, workers ),
+		"SmolLocal"  => local_workload       ( SmolLocal::new()               , workload, config, workers ),
+		"ThreadPool" => handle_workload      ( thread_pool( threads, pinning ), workload, config, workers ),
+		"TokioTp"    => handle_workload      ( tokio_tp( threads, pinning )   , workload, config, workers ),
+		"AsyncStd"   => handle_workload      ( async_std( threads, pinning )  , workload, config, workers ),
+		"SmolTp"     => spawn_workload       ( smol_tp( threads, pinning )    , workload, config, workers ),
+
+		// The native rings, each on the executor it is written for.
+		//
+		"futures::executor::LocalPool" =>
+		{
+			Some( each( LocalPool::new(), move |exec| async move { LocalPoolNativeRing::with_config( config ).run( exec ).await } ) )
+		}
+
+		"tokio::runtime::Runtime (basic_scheduler + LocalSet)" =>
+		{
+			let pool = InLocalSet( Builder::new().basic_scheduler().build().expect( "build tokio basic_scheduler" ) );
+
+			Some( each( pool, move |()| async move { TokioCtNativeRing::with_config( config ).run().await } ) )
+		}
+
+		"tokio::runtime::Runtime" =>
+		{
+			let mut builder = tokio_builder( threads, pinning );
+			builder.threaded_scheduler();
+
+			let pool = builder.build().expect( "build tokio threadpool" );
+
+			Some( each( pool, move |exec| async move { TokioTpNativeRing::with_config( config ).run( exec ).await } ) )
+		}
+
+		"async_executor::LocalExecutor" =>
+		{
+			Some( each( SmolLocal::new(), move |exec| async move { SmolLocalNativeRing::with_config( config ).run( exec.executor().clone() ).await } ) )
+		}
+
+		"async_executor::Executor" =>
+		{
+			Some( each( smol_tp( threads, pinning ), move |exec| async move { SmolNativeRing::with_config( config ).run( exec.executor().clone() ).await } ) )
+		}
+
+		"futures::executor::ThreadPool" =>
+		{
+			Some( each( thread_pool( threads, pinning ), move |exec| async move { ThreadPoolNativeRing::with_config( config ).run( exec ).await } ) )
+		}
+
+		"async_std::task" =>
+		{
+			start_async_std( threads, pinning );
+
+			Some( each( AsyncStdTask, move |()| async move { AsyncStdNativeRing::with_config( config ).run().await } ) )
+		}
+
+		_ => None,
+	}
+}
+
+
+
+// How a routine drives an executor.
+//
+trait BenchExecutor: 'static
+{
+	// What the workload spawns it's tasks on.
+	//
+	type Spawner;
+
+	fn spawner( &self ) -> Self::Spawner;
+
+	// Run a future to completion, blocking the thread that runs the routine.
+	//
+	fn block_on<F: Future>( &mut self, future: F ) -> F::Output;
+}
+
+
+// A routine that gives `iteration` a spawner and runs it on `exec`.
+//
+fn each<E, F, Fut>( mut exec: E, mut iteration: F ) -> Routine
+
+	where E  : BenchExecutor                        ,
+	      F  : FnMut( E::Spawner ) -> Fut + 'static ,
+	      Fut: Future<Output = ()>                  ,
+{
+	Box::new( move ||
+	{
+		let spawner = exec.spawner();
+
+		exec.block_on( iteration( spawner ) );
+	})
+}
+
+
+
+// The workloads of executors that spawn `Send` tasks.
+//
+fn spawn_workload<E>( exec: E, workload: &str, config: RingConfig, workers: usize ) -> Option<Routine>
+
+	where E: BenchExecutor, E::Spawner: Spawn + Clone + Send + Sync + 'static
+{
+	let nodes = config.nodes;
+
+	let routine = match workload
+	{
+		"BoundedRing"  => each( exec, move |s| async move { BoundedRing::with_config( config ).run( s ).await } ),
+		"LatencyRing"  => each( exec, move |s| async move { LatencyRing::with_config( config ).run( s ).await } ),
+		"SpawnLatency" => each( exec, move |s| async move { SpawnLatency::new( nodes ).run( s ).await         } ),
+
+		_ => return any_workload( exec, workload, config, workers ),
+	};
+
+	Some( routine )
+}
+
+
+// The workloads of executors that also implement `SpawnHandle`.
+//
+fn handle_workload<E>( exec: E, workload: &str, config: RingConfig, workers: usize ) -> Option<Routine>
+
+	where E: BenchExecutor, E::Spawner: SpawnHandle<()> + SpawnHandle<Duration> + Spawn + Clone + Send + Sync + 'static
+{
+	let nodes = config.nodes;
+
+	let routine = match workload
+	{
+		"HandleRing"         => each( exec, move |s| async move { HandleRing::with_config( config ).run( s ).await } ),
+		"SpawnHandleLatency" => each( exec, move |s| async move { SpawnLatency::new( nodes ).run_handle( s ).await } ),
+
+		_ => return spawn_workload( exec, workload, config, workers ),
+	};
+
+	Some( routine )
+}
+
+
+// The workloads of single threaded executors, their nodes don't have to be `Send`.
+//
+fn local_workload<E>( exec: E, workload: &str, config: RingConfig, workers: usize ) -> Option<Routine>
+
+	where E: BenchExecutor, E::Spawner: LocalSpawn + Spawn + Clone + 'static
+{
+	let nodes = config.nodes;
+
+	let routine = match workload
+	{
+		"LocalRing"         => each( exec, move |s| async move { LocalRing  ::with_config( config ).run      ( s ).await } ),
+		"BoundedRing"       => each( exec, move |s| async move { BoundedRing::with_config( config ).run_local( s ).await } ),
+		"LatencyRing"       => each( exec, move |s| async move { LatencyRing::with_config( config ).run_local( s ).await } ),
+		"SpawnLocalLatency" => each( exec, move |s| async move { SpawnLatency::new( nodes ).run_local( s ).await          } ),
+
+		_ => return any_workload( exec, workload, config, workers ),
+	};
+
+	Some( routine )
+}
+
+
+// The workloads of single threaded executors that also implement `LocalSpawnHandle`.
+//
+fn local_handle_workload<E>( exec: E, workload: &str, config: RingConfig, workers: usize ) -> Option<Routine>
+
+	where E: BenchExecutor, E::Spawner: LocalSpawnHandle<()> + LocalSpawnHandle<Duration> + LocalSpawn + Spawn + Clone + 'static
+{
+	let nodes = config.nodes;
+
+	let routine = match workload
+	{
+		"LocalHandleRing"         => each( exec, move |s| async move { LocalHandleRing::with_config( config ).run( s ).await } ),
+		"LocalSpawnHandleLatency" => each( exec, move |s| async move { SpawnLatency::new( nodes ).run_local_handle( s ).await } ),
+
+		_ => return local_workload( exec, workload, config, workers ),
+	};
+
+	Some( routine )
+}
+
+
+// The workloads that only need `Spawn`, on any executor.
+//
+fn any_workload<E>( exec: E, workload: &str, config: RingConfig, workers: usize ) -> Option<Routine>
+
+	where E: BenchExecutor, E::Spawner: Spawn + 'static
+{
+	let nodes = config.nodes;
+
+	let routine = match workload
+	{
+		"WakeLatency"       => each( exec, move |s| async move { WakeLatency ::new( nodes          ).run       ( s ).await } ),
+		"RemoteWakeLatency" => each( exec, move |s| async move { WakeLatency ::new( nodes          ).run_remote( s ).await } ),
+		"Fairness"          => each( exec, move |s| async move { Fairness    ::new( nodes, workers ).run       ( s ).await } ),
+		"IdleTasks"         => each( exec, move |s| async move { IdleTasks   ::new( nodes          ).run       ( s ).await } ),
+		"WorkStealing"      => each( exec, move |s| async move { WorkStealing::new( nodes, workers ).run       ( s ).await } ),
+
+		_ => return None,
+	};
+
+	Some( routine )
+}
+
+
+
+impl BenchExecutor for LocalPool
+{
+	type Spawner = LocalSpawner;
+
+	fn spawner( &self ) -> LocalSpawner { LocalPool::spawner( self ) }
+
+	fn block_on<F: Future>( &mut self, future: F ) -> F::Output { self.run_until( future ) }
+}
+
+
+impl BenchExecutor for MiniExec
+{
+	type Spawner = MiniSpawner;
+
+	fn spawner( &self ) -> MiniSpawner { MiniExec::spawner( self ) }
+
+	fn block_on<F: Future>( &mut self, future: F ) -> F::Output { self.run_until( future ) }
+}
+
+
+impl BenchExecutor for TokioCt
+{
+	type Spawner = TokioCt;
+
+	fn spawner( &self ) -> TokioCt { self.clone() }
+
+	fn block_on<F: Future>( &mut self, future: F ) -> F::Output { TokioCt::block_on( self, future ) }
+}
+
+
+impl BenchExecutor for SmolLocal
+{
+	type Spawner = SmolLocal;
+
+	fn spawner( &self ) -> SmolLocal { self.clone() }
+
+	fn block_on<F: Future>( &mut self, future: F ) -> F::Output { SmolLocal::block_on( self, future ) }
+}
+
+
+impl BenchExecutor for ThreadPool
+{
+	type Spawner = ThreadPool;
+
+	fn spawner( &self ) -> ThreadPool { self.clone() }
+
+	fn block_on<F: Future>( &mut self, future: F ) -> F::Output { block_on( future ) }
+}
+
+
+impl BenchExecutor for TokioTp
+{
+	type Spawner = TokioTp;
+
+	fn spawner( &self ) -> TokioTp { self.clone() }
+
+	fn block_on<F: Future>( &mut self, future: F ) -> F::Output { TokioTp::block_on( self, future ) }
+}
+
+
+impl BenchExecutor for AsyncStd
+{
+	type Spawner = AsyncStd;
+
+	fn spawner( &self ) -> AsyncStd { *self }
+
+	fn block_on<F: Future>( &mut self, future: F ) -> F::Output { AsyncStd::block_on( future ) }
+}
+
+
+impl BenchExecutor for SmolTp
+{
+	type Spawner = SmolTp;
+
+	fn spawner( &self ) -> SmolTp { self.clone() }
+
+	fn block_on<F: Future>( &mut self, future: F ) -> F::Output { SmolTp::block_on( self, future ) }
+}
+
+
+impl BenchExecutor for Runtime
+{
+	type Spawner = Handle;
+
+	fn spawner( &self ) -> Handle { self.handle().clone() }
+
+	fn block_on<F: Future>( &mut self, future: F ) -> F::Output { Runtime::block_on( self, future ) }
+}
+
+
+// A tokio runtime that runs every iteration in a new `LocalSet`, so it can spawn tasks that aren't `Send`.
+//
+struct InLocalSet( Runtime );
+
+
+impl BenchExecutor for InLocalSet
+{
+	type Spawner = ();
+
+	fn spawner( &self ) {}
+
+	// Run the future rather than spawning it, so a panic reaches us.
+	//
+	fn block_on<F: Future>( &mut self, future: F ) -> F::Output { self.0.block_on( LocalSet::new().run_until( future ) ) }
+}
+
+
+// The global async-std runtime, without the wrapper.
+//
+struct AsyncStdTask;
+
+
+impl BenchExecutor for AsyncStdTask
+{
+	type Spawner = ();
+
+	fn spawner( &self ) {}
+
+	fn block_on<F: Future>( &mut self, future: F ) -> F::Output { async_std::task::block_on( future ) }
+}
+
+
+
+fn tokio_ct() -> TokioCt
+{
+	TokioCt::try_from( &mut Builder::new() ).expect( "build tokio basic_scheduler" )
+}
+
+
+// The number of worker threads `executor` runs tasks on, 1 for the single threaded ones.
+//
+fn workers( executor: &str, threads: Option<usize> ) -> usize
+{
+	match executor
+	{
+		"ThreadPool" | "TokioTp" | "SmolTp" => threads.unwrap_or_else( num_cpus::get ),
+		"AsyncStd"                          => threads.unwrap_or_else( async_std_threads ),
+		_                                   => 1,
+	}
+}
+
+
+fn tokio_tp( threads: Option<usize>, pinning: &Pinning ) -> TokioTp
+{
+	TokioTp::try_from( &mut tokio_builder( threads, pinning ) ).expect( "build tokio threadpool" )
+}
+
+
+fn async_std( threads: Option<usize>, pinning: &Pinning ) -> AsyncStd
+{
+	start_async_std( threads, pinning );
+
+	AsyncStd::default()
+}
+
+
+// SmolTp starts it's own threads, which we can't pin, see `Pinning::unpinned`.
+//
+fn smol_tp( threads: Option<usize>, pinning: &Pinning ) -> SmolTp
+{
+	pinning.unpinned( || threads.map( SmolTp::new ).unwrap_or_default() )
+}
+
+
+fn thread_pool( threads: Option<usize>, pinning: &Pinning ) -> ThreadPool
+{
+	let mut builder = ThreadPool::builder();
+
+	if let Some( threads ) = threads { builder.pool_size( threads ); }
+
+	pinning.pool( &mut builder );
+
+	builder.create().expect( "create threadpool" )
+}
+
+
+fn tokio_builder( threads: Option<usize>, pinning: &Pinning ) -> Builder
+{
+	let mut builder = Builder::new();
+
+	if let Some( threads ) = threads { builder.core_threads( threads ); }
+
+	pinning.tokio( &mut builder, threads );
+
+	builder
+}
+
+
+
+#[ cfg( test ) ]
+//
+mod tests
+{
+	use super::*;
+
+	#[test]
+	//
+	fn unknown_combination()
+	{
+		let config = RingConfig::new( 10, Forward::Detach );
+
+		assert!( routine( "ThreadPool", "LocalRing", config, None, &Pinning::default() ).is_none() );
+	}
+
+
+	// These don't depend on async_executors, so they can run anywhere.
+	//
+	#[test]
+	//
+	fn native_routines_run()
+	{
+		let config = RingConfig::new( 10, Forward::Detach ).laps( 2 ).capacity( 2 );
+
+		// Another test might have started async-std with an other number of threads.
+		//
+		for (executor, workload) in ROUTINES.iter().filter( |(e, w)| w.ends_with( "NativeRing" ) && threads_fit( e, Some( 2 ) ) )
+		{
+			let mut routine = routine( executor, workload, config, Some( 2 ), &Pinning::default() ).expect( "create routine" );
+
+			routine();
+			routine();
+		}
+	}
+}
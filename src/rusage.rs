@@ -0,0 +1,261 @@
+//! Resource usage of the process from `getrusage`, to report the CPU time, context switches and page
+//! faults per benchmark iteration next to the timings.
+//!
+//! The usage is that of the whole process, so it includes the worker threads of the executors. That
+//! is the point: a multithreaded executor can finish sooner while burning a lot more CPU, which only
+//! shows when comparing the CPU time to the wall time. `timed` adds the usage of every batch to the
+//! record of the benchmark.
+//!
+//! Only available on unix.
+//!
+use crate::Record;
+use serde::{ Deserialize, Serialize };
+use std::{ fmt, ops::Sub, time::Duration };
+
+
+// Resource usage counters. Times are in nanoseconds.
+//
+#[ derive( Clone, Copy, Debug, Default, PartialEq, Eq ) ]
+//
+pub struct ResourceUsage
+{
+	pub user_ns             : u64 ,
+	pub sys_ns              : u64 ,
+	pub voluntary_switches  : u64 ,
+	pub involuntary_switches: u64 ,
+	pub minor_faults        : u64 ,
+	pub major_faults        : u64 ,
+}
+
+
+impl ResourceUsage
+{
+	// Add the counters of `other`.
+	//
+	pub fn add( &mut self, other: ResourceUsage )
+	{
+		self.user_ns              += other.user_ns              ;
+		self.sys_ns               += other.sys_ns               ;
+		self.voluntary_switches   += other.voluntary_switches   ;
+		self.involuntary_switches += other.involuntary_switches ;
+		self.minor_faults         += other.minor_faults         ;
+		self.major_faults         += other.major_faults         ;
+	}
+}
+
+
+impl Sub for ResourceUsage
+{
+	type Output = Self;
+
+	fn sub( self, other: Self ) -> Self
+	{
+		Self
+		{
+			user_ns             : self.user_ns              - other.user_ns              ,
+			sys_ns              : self.sys_ns               - other.sys_ns               ,
+			voluntary_switches  : self.voluntary_switches   - other.voluntary_switches   ,
+			involuntary_switches: self.involuntary_switches - other.involuntary_switches ,
+			minor_faults        : self.minor_faults         - other.minor_faults         ,
+			major_faults        : self.major_faults         - other.major_faults         ,
+		}
+	}
+}
+
+
+// The resource usage of the process so far. `None` if `getrusage` is not available or fails.
+//
+#[ cfg( unix ) ]
+//
+pub fn resource_usage() -> Option<ResourceUsage>
+{
+	let time = |t: libc::timeval| t.tv_sec as u64 * 1_000_000_000 + t.tv_usec as u64 * 1_000;
+
+	// Safety: rusage is plain data, so all zeroes is valid, and getrusage only writes to it.
+	//
+	let usage = unsafe
+	{
+		let mut usage: libc::rusage = std::mem::zeroed();
+
+		if libc::getrusage( libc::RUSAGE_SELF, &mut usage ) != 0
+		{
+			return None;
+		}
+
+		usage
+	};
+
+	Some( ResourceUsage
+	{
+		user_ns             : time( usage.ru_utime ) ,
+		sys_ns              : time( usage.ru_stime ) ,
+		voluntary_switches  : usage.ru_nvcsw  as u64 ,
+		involuntary_switches: usage.ru_nivcsw as u64 ,
+		minor_faults        : usage.ru_minflt as u64 ,
+		major_faults        : usage.ru_majflt as u64 ,
+	})
+}
+
+
+// The resource usage of the process so far. `None` if `getrusage` is not available or fails.
+//
+#[ cfg( not( unix ) ) ]
+//
+pub fn resource_usage() -> Option<ResourceUsage>
+{
+	None
+}
+
+
+
+// Resource usage per iteration of a benchmark.
+//
+#[ derive( Clone, Debug, PartialEq, Serialize, Deserialize ) ]
+//
+pub struct UsageStats
+{
+	pub user_ns             : f64 ,
+	pub sys_ns              : f64 ,
+	pub voluntary_switches  : f64 ,
+	pub involuntary_switches: f64 ,
+	pub minor_faults        : f64 ,
+	pub major_faults        : f64 ,
+
+	// CPU time divided by wall time, ie. how many cores were busy on average.
+	//
+	pub cpu_ratio: f64,
+}
+
+
+impl UsageStats
+{
+	// `elapsed` is the wall time of all the iterations. The ratio divides the totals, as batches
+	// with more iterations also used more CPU time.
+	//
+	pub fn new( total: ResourceUsage, iterations: u64, elapsed: Duration ) -> Self
+	{
+		let iterations = iterations.max( 1 ) as f64;
+		let user_ns    = total.user_ns as f64 / iterations;
+		let sys_ns     = total.sys_ns  as f64 / iterations;
+
+		let cpu_ratio = match elapsed.as_nanos()
+		{
+			0    => 0.0,
+			wall => ( total.user_ns + total.sys_ns ) as f64 / wall as f64,
+		};
+
+		Self
+		{
+			user_ns                                                              ,
+			sys_ns                                                               ,
+			voluntary_switches  : total.voluntary_switches   as f64 / iterations ,
+			involuntary_switches: total.involuntary_switches as f64 / iterations ,
+			minor_faults        : total.minor_faults         as f64 / iterations ,
+			major_faults        : total.major_faults         as f64 / iterations ,
+			cpu_ratio                                                            ,
+		}
+	}
+
+
+	pub fn from_record( record: &Record ) -> Option<Self>
+	{
+		Some( Self::new( record.usage?, record.iterations, record.elapsed ) )
+	}
+}
+
+
+
+// Prints a table of the resource usage per iteration in a set of records. Records without usage are skipped.
+//
+pub struct UsageReport( pub Vec<( String, UsageStats )> );
+
+
+impl UsageReport
+{
+	pub fn new( records: &[Record] ) -> Self
+	{
+		Self( records.iter().filter_map( |r| Some(( r.id.to_string(), UsageStats::from_record( r )? )) ).collect() )
+	}
+
+
+	pub fn is_empty( &self ) -> bool
+	{
+		self.0.is_empty()
+	}
+}
+
+
+impl fmt::Display for UsageReport
+{
+	fn fmt( &self, f: &mut fmt::Formatter<'_> ) -> fmt::Result
+	{
+		let width = self.0.iter().map( |(id, _)| id.len() ).max().unwrap_or( 0 );
+		let ns    = |ns: f64| format!( "{:?}", Duration::from_nanos( ns as u64 ) );
+
+		writeln!( f, "Resource usage per iteration:" )?;
+		writeln!( f )?;
+
+		writeln!
+		(
+			f, "  {:<width$}  {:>12}  {:>12}  {:>6}  {:>10}  {:>10}  {:>10}  {:>10}",
+			"", "user", "sys", "cpu", "vol csw", "invol csw", "minflt", "majflt", width = width,
+		)?;
+
+		for (id, u) in &self.0
+		{
+			writeln!
+			(
+				f, "  {:<width$}  {:>12}  {:>12}  {:>6.2}  {:>10.1}  {:>10.1}  {:>10.1}  {:>10.1}",
+				id, ns( u.user_ns ), ns( u.sys_ns ), u.cpu_ratio, u.voluntary_switches, u.involuntary_switches,
+				u.minor_faults, u.major_faults, width = width,
+			)?;
+		}
+
+		Ok(())
+	}
+}
+
+
+
+#[ cfg( test ) ]
+//
+mod tests
+{
+	use super::*;
+
+	#[test]
+	//
+	fn per_iteration()
+	{
+		let total = ResourceUsage { user_ns: 3000, sys_ns: 1000, voluntary_switches: 8, ..Default::default() };
+		let stats = UsageStats::new( total, 4, Duration::from_nanos( 2000 ) );
+
+		assert_eq!( 750.0, stats.user_ns            );
+		assert_eq!( 250.0, stats.sys_ns             );
+		assert_eq!( 2.0  , stats.voluntary_switches );
+		assert_eq!( 2.0  , stats.cpu_ratio          );
+	}
+
+
+	#[ cfg( unix ) ]
+	//
+	#[test]
+	//
+	fn cpu_time_grows()
+	{
+		let before = resource_usage().expect( "getrusage" );
+		let start  = std::time::Instant::now();
+
+		// Burn some CPU.
+		//
+		let mut x = 0u64;
+
+		while start.elapsed() < Duration::from_millis( 50 ) { x = x.wrapping_add( 1 ); }
+
+		assert!( x > 0 );
+
+		let used = resource_usage().expect( "getrusage" ) - before;
+
+		assert!( used.user_ns + used.sys_ns > 0 );
+	}
+}
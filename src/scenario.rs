@@ -0,0 +1,319 @@
+//! Benchmark matrices described in YAML files. A scenario lists the routines to run and the values
+//! of each parameter, and expands into one benchmark per combination. See `scenarios/ring.yml`.
+//!
+use crate::{ BenchId, Forward, RingConfig, ROUTINES, in_default_matrix, supported };
+use serde::Deserialize;
+use std::{ collections::BTreeMap, fs, path::Path };
+
+
+// The scenario file used when none is given.
+//
+pub const DEFAULT_SCENARIO: &str = "scenarios/ring.yml";
+
+// The environment variable to choose another scenario file for `cargo bench --bench ring`.
+//
+pub const SCENARIO_VAR: &str = "BENCH_SCENARIO";
+
+
+// A matrix of benchmarks. Parameters that are left out of the file take the defaults of `RingConfig`,
+// forward defaults to all modes and routines to all entries of `ROUTINES`, see `in_default_matrix`.
+//
+#[ derive( Clone, Debug, Deserialize ) ]
+#[ serde( deny_unknown_fields ) ]
+//
+pub struct Scenario
+{
+	// The name of the criterion group.
+	//
+	pub group: String,
+
+	#[ serde( default ) ] pub routines: Vec<RoutineSpec> ,
+	                      pub nodes   : Vec<usize>       ,
+	#[ serde( default ) ] pub forward : Vec<Forward>     ,
+	#[ serde( default ) ] pub capacity: Vec<usize>       ,
+	#[ serde( default ) ] pub laps    : Vec<usize>       ,
+
+	// Worker threads for the multithreaded executors. Leave out to use the executor defaults.
+	//
+	#[ serde( default ) ] pub threads: Vec<usize>,
+
+	pub samples: Samples,
+}
+
+
+// An executor and a workload, as they appear in `ROUTINES` or `LATENCY_ROUTINES`.
+//
+#[ derive( Clone, Debug, Deserialize ) ]
+#[ serde( deny_unknown_fields ) ]
+//
+pub struct RoutineSpec
+{
+	pub executor: String,
+	pub workload: String,
+}
+
+
+// The criterion sample size. Big rings take long, so they can have a smaller sample size.
+//
+#[ derive( Clone, Debug, Deserialize ) ]
+#[ serde( deny_unknown_fields ) ]
+//
+pub struct Samples
+{
+	pub default: usize,
+
+	// Sample size by node count.
+	//
+	#[ serde( default ) ] pub nodes: BTreeMap<usize, usize>,
+}
+
+
+// One benchmark of the expanded matrix.
+//
+#[ derive( Clone, Debug ) ]
+//
+pub struct Entry
+{
+	pub id      : BenchId       ,
+	pub executor: String        ,
+	pub workload: String        ,
+	pub config  : RingConfig    ,
+	pub threads : Option<usize> ,
+	pub samples : usize         ,
+}
+
+
+impl Scenario
+{
+	// Read and validate a scenario file.
+	//
+	pub fn load( path: impl AsRef<Path> ) -> Result<Self, String>
+	{
+		let path = path.as_ref();
+		let yaml = fs::read_to_string( path ).map_err( |e| format!( "read {}: {}", path.display(), e ) )?;
+
+		Self::parse( &yaml ).map_err( |e| format!( "{}: {}", path.display(), e ) )
+	}
+
+
+	// Parse and validate a scenario.
+	//
+	pub fn parse( yaml: &str ) -> Result<Self, String>
+	{
+		let scenario: Self = serde_yaml::from_str( yaml ).map_err( |e| e.to_string() )?;
+
+		scenario.validate()?;
+
+		Ok( scenario )
+	}
+
+
+	fn validate( &self ) -> Result<(), String>
+	{
+		if self.nodes.is_empty() { return Err( "nodes can not be empty".to_string() ) }
+
+		if self.nodes   .iter().any( |n| *n < 2 ) { return Err( "a ring needs at least 2 nodes".to_string() ) }
+		if self.capacity.iter().any( |c| *c < 1 ) { return Err( "capacity must be at least 1"  .to_string() ) }
+		if self.laps    .iter().any( |l| *l < 1 ) { return Err( "laps must be at least 1"      .to_string() ) }
+		if self.threads .iter().any( |t| *t < 1 ) { return Err( "threads must be at least 1"   .to_string() ) }
+
+		if self.samples.nodes.values().chain( Some( &self.samples.default ) ).any( |s| *s < 10 )
+		{
+			return Err( "criterion needs at least 10 samples".to_string() );
+		}
+
+		for spec in &self.routines
+		{
+			if !supported( &spec.executor, &spec.workload )
+			{
+				return Err( format!( "{} can not run {}", spec.executor, spec.workload ) );
+			}
+		}
+
+		Ok(())
+	}
+
+
+	// Expand the matrix. The order is nodes, forward, capacity, laps, threads and finally the routines,
+	// so that all executors are benchmarked one after the other for each set of parameters.
+	//
+	// The id of an entry only has the parameters capacity, laps and threads if they are given in the file,
+	// so that the default matrix can be compared with runs from before they existed. Without routines in
+	// the file, the handle rings only run with `Forward::Await`, see `in_default_matrix`.
+	//
+	pub fn entries( &self ) -> Vec<Entry>
+	{
+		let default  = RingConfig::new( 2, Forward::Detach );
+		let capacity = [ default.capacity ];
+		let laps     = [ default.laps     ];
+
+		let forward  = or( &self.forward , &Forward::ALL );
+		let capacity = or( &self.capacity, &capacity     );
+		let laps     = or( &self.laps    , &laps         );
+
+		let threads: Vec<Option<usize>> = match self.threads.is_empty()
+		{
+			true  => vec![ None ],
+			false => self.threads.iter().copied().map( Some ).collect(),
+		};
+
+		let routines: Vec<( &str, &str )> = match self.routines.is_empty()
+		{
+			true  => ROUTINES.to_vec(),
+			false => self.routines.iter().map( |r| ( r.executor.as_str(), r.workload.as_str() ) ).collect(),
+		};
+
+		let mut configs = Vec::new();
+
+		for &nodes in &self.nodes
+		{
+			for &forward in forward
+			{
+				for &capacity in capacity
+				{
+					for &laps in laps
+					{
+						configs.push( RingConfig::new( nodes, forward ).capacity( capacity ).laps( laps ) );
+					}
+				}
+			}
+		}
+
+
+		let mut entries = Vec::new();
+
+		for config in configs
+		{
+			for &threads in &threads
+			{
+				for &(executor, workload) in &routines
+				{
+					if self.routines.is_empty() && !in_default_matrix( workload, config.forward ) { continue }
+
+					entries.push( self.entry( executor, workload, config, threads ) );
+				}
+			}
+		}
+
+		entries
+	}
+
+
+	fn entry( &self, executor: &str, workload: &str, config: RingConfig, threads: Option<usize> ) -> Entry
+	{
+		let mut id = BenchId::new( self.group.as_str(), executor, workload )
+
+			.param( "forward", config.forward )
+			.param( "nodes"  , config.nodes   )
+		;
+
+		if !self.capacity.is_empty() { id = id.param( "capacity", config.capacity ); }
+		if !self.laps    .is_empty() { id = id.param( "laps"    , config.laps     ); }
+
+		if let Some( threads ) = threads { id = id.param( "threads", threads ); }
+
+		Entry
+		{
+			id                                         ,
+			executor: executor.to_string()             ,
+			workload: workload.to_string()             ,
+			config                                     ,
+			threads                                    ,
+			samples : self.sample_size( config.nodes ) ,
+		}
+	}
+
+
+	fn sample_size( &self, nodes: usize ) -> usize
+	{
+		*self.samples.nodes.get( &nodes ).unwrap_or( &self.samples.default )
+	}
+}
+
+
+fn or<'a, T>( values: &'a [T], default: &'a [T] ) -> &'a [T]
+{
+	if values.is_empty() { default } else { values }
+}
+
+
+
+#[ cfg( test ) ]
+//
+mod tests
+{
+	use super::*;
+
+	#[test]
+	//
+	fn expand()
+	{
+		// YAML does not allow tabs for indentation.
+		//
+		let yaml = "
+group   : Test
+routines: [ { executor: TokioTp, workload: HandleRing }, { executor: LocalPool, workload: LocalRing } ]
+nodes   : [ 10, 100 ]
+forward : [ detach ]
+laps    : [ 1, 2 ]
+samples : { default: 20, nodes: { 10: 100 } }
+";
+
+		let scenario = Scenario::parse( yaml ).expect( "parse scenario" );
+
+		let entries = scenario.entries();
+
+		assert_eq!( 8, entries.len() );
+
+		assert_eq!( "TokioTp HandleRing detach 10 1"  , entries[0].id.to_string() );
+		assert_eq!( "LocalPool LocalRing detach 100 2", entries[7].id.to_string() );
+		assert_eq!( 100, entries[0].samples );
+		assert_eq!( 20 , entries[7].samples );
+		assert_eq!( 2  , entries[7].config.laps );
+		assert_eq!( 1  , entries[7].config.capacity );
+	}
+
+
+	#[test]
+	//
+	fn handle_rings_only_await()
+	{
+		let scenario = Scenario::parse( "{ group: T, nodes: [ 2 ], forward: [ detach, await ], samples: { default: 20 } }" ).expect( "parse scenario" );
+
+		let handle: Vec<String> = scenario.entries().into_iter()
+
+			.filter( |e| e.workload.ends_with( "HandleRing" ) )
+			.map   ( |e| e.id.get( "forward" ).expect( "forward" ).to_string() )
+			.collect()
+		;
+
+		assert!( !handle.is_empty() );
+		assert!( handle.iter().all( |f| f == "await" ) );
+	}
+
+
+	#[test]
+	//
+	fn invalid()
+	{
+		assert!( Scenario::parse( "{ group: T, nodes: [ 1 ], samples: { default: 20 } }" ).is_err() );
+		assert!( Scenario::parse( "{ group: T, nodes: [ 2 ], samples: { default: 20 }, size: 3 }" ).is_err() );
+
+		assert!( Scenario::parse( "{ group: T, nodes: [ 2 ], samples: { default: 20 }, routines: [ { executor: TokioTp, workload: LocalRing } ] }" ).is_err() );
+	}
+
+
+	// The scenarios that ship with the crate should be valid.
+	//
+	#[test]
+	//
+	fn shipped()
+	{
+		for entry in fs::read_dir( "scenarios" ).expect( "read scenarios dir" )
+		{
+			let path = entry.expect( "dir entry" ).path();
+
+			Scenario::load( &path ).expect( "load scenario" );
+		}
+	}
+}
@@ -0,0 +1,204 @@
+//! Adapters that let the executors of the smol family (`async-executor`) run the generic rings,
+//! by implementing the futures `Spawn` and `LocalSpawn` traits.
+//!
+use async_executor::{ Executor, LocalExecutor };
+use futures::{ executor::block_on, channel::oneshot, future::{ Future, FutureObj, LocalFutureObj }, task::{ LocalSpawn, Spawn, SpawnError } };
+use std::{ rc::Rc, sync::Arc, thread };
+
+
+// A multithreaded `async_executor::Executor`. The executor doesn't create threads itself,
+// so this runs it on a number of threads that live until the last clone is dropped.
+//
+#[ derive( Clone ) ]
+//
+pub struct SmolTp
+{
+	inner: Arc<SmolTpInner>,
+}
+
+
+struct SmolTpInner
+{
+	exec: Arc< Executor<'static> > ,
+
+	// Dropping these stops the worker threads. They are not joined, because the last
+	// clone of SmolTp might be dropped on one of the workers.
+	//
+	_stop: Vec< oneshot::Sender<()> > ,
+}
+
+
+impl SmolTp
+{
+	// Create an executor running on `threads` worker threads.
+	//
+	pub fn new( threads: usize ) -> Self
+	{
+		assert!( threads > 0 );
+
+		let     exec = Arc::new( Executor::new() );
+		let mut stop = Vec::with_capacity( threads );
+
+		for i in 0..threads
+		{
+			let (tx, rx) = oneshot::channel::<()>();
+			let exec2    = exec.clone();
+
+			thread::Builder::new()
+
+				.name( format!( "smol-worker-{}", i ) )
+				.spawn( move || { let _ = block_on( exec2.run( rx ) ); } )
+				.expect( "spawn smol worker thread" )
+			;
+
+			stop.push( tx );
+		}
+
+		Self { inner: Arc::new( SmolTpInner { exec, _stop: stop } ) }
+	}
+
+
+	// The underlying executor, for running native code on it.
+	//
+	pub fn executor( &self ) -> &Arc< Executor<'static> >
+	{
+		&self.inner.exec
+	}
+
+
+	// Block the current thread until the future is complete. The current thread does not
+	// take part in running the tasks.
+	//
+	pub fn block_on<F: Future>( &self, future: F ) -> F::Output
+	{
+		block_on( future )
+	}
+}
+
+
+impl Default for SmolTp
+{
+	// One worker per core.
+	//
+	fn default() -> Self
+	{
+		Self::new( num_cpus::get() )
+	}
+}
+
+
+impl Spawn for SmolTp
+{
+	fn spawn_obj( &self, future: FutureObj<'static, ()> ) -> Result<(), SpawnError>
+	{
+		self.inner.exec.spawn( future ).detach();
+
+		Ok(())
+	}
+}
+
+
+
+// A single threaded `async_executor::LocalExecutor`, run on the thread that calls `block_on`.
+//
+#[ derive( Clone ) ]
+//
+pub struct SmolLocal
+{
+	exec: Rc< LocalExecutor<'static> >,
+}
+
+
+impl SmolLocal
+{
+	pub fn new() -> Self
+	{
+		Self { exec: Rc::new( LocalExecutor::new() ) }
+	}
+
+
+	// The underlying executor, for running native code on it.
+	//
+	pub fn executor( &self ) -> &Rc< LocalExecutor<'static> >
+	{
+		&self.exec
+	}
+
+
+	// Run the executor on the current thread until the future is complete.
+	//
+	pub fn block_on<F: Future>( &self, future: F ) -> F::Output
+	{
+		block_on( self.exec.run( future ) )
+	}
+}
+
+
+impl Default for SmolLocal
+{
+	fn default() -> Self
+	{
+		Self::new()
+	}
+}
+
+
+impl LocalSpawn for SmolLocal
+{
+	fn spawn_local_obj( &self, future: LocalFutureObj<'static, ()> ) -> Result<(), SpawnError>
+	{
+		self.exec.spawn( future ).detach();
+
+		Ok(())
+	}
+}
+
+
+impl Spawn for SmolLocal
+{
+	fn spawn_obj( &self, future: FutureObj<'static, ()> ) -> Result<(), SpawnError>
+	{
+		self.exec.spawn( future ).detach();
+
+		Ok(())
+	}
+}
+
+
+
+#[ cfg( test ) ]
+//
+mod tests
+{
+	use super::*;
+	use crate::{ BoundedRing, LocalRing, Forward };
+
+	#[test]
+	//
+	fn threadpool_runs_ring()
+	{
+		let exec = SmolTp::new( 2 );
+
+		for forward in Forward::ALL.iter()
+		{
+			let mut ring = BoundedRing::new( 10, *forward );
+
+			exec.block_on( ring.run( exec.clone() ) );
+		}
+	}
+
+
+	#[test]
+	//
+	fn local_runs_ring()
+	{
+		let exec = SmolLocal::new();
+
+		for forward in Forward::ALL.iter()
+		{
+			let mut ring = LocalRing::new( 10, *forward );
+
+			exec.block_on( ring.run( exec.clone() ) );
+		}
+	}
+}
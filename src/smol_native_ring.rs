@@ -0,0 +1,87 @@
+//! This uses bounded channels and has the possibility to spawn each message forwarding on the executor
+//! rather than awaiting. See [`Forward`](crate::Forward).
+//!
+use futures::channel::mpsc;
+use std::{ future::Future, sync::Arc };
+use async_executor::{ Executor, Task };
+use crate::{ Forward, Message, Node, Ring, RingConfig, RingSpawn };
+
+
+pub struct SmolNativeRing( Ring );
+
+
+impl SmolNativeRing
+{
+	// Create channels between all the nodes.
+	//
+	pub fn new( n: usize, forward: Forward ) -> Self
+	{
+		Self::with_config( RingConfig::new( n, forward ) )
+	}
+
+
+	// Create a ring with a custom channel capacity and number of laps.
+	//
+	pub fn with_config( config: RingConfig ) -> Self
+	{
+		Self( Ring::with_config( config ) )
+	}
+
+
+	// Run the benchmark.
+	//
+	pub async fn run( &mut self, exec: Arc< Executor<'static> > )
+	{
+		self.0.run( exec ).await;
+	}
+}
+
+
+
+impl RingSpawn for Arc< Executor<'static> >
+{
+	type Handle = Task<()>;
+
+	fn spawn_node<M: Message>( &self, node: Node<M>, done: mpsc::Sender<()> )
+	{
+		self.spawn( node.run( self.clone(), done ) ).detach();
+	}
+
+	fn detach( &self, task: impl Future<Output = ()> + Send + 'static )
+	{
+		self.spawn( task ).detach();
+	}
+
+	fn spawn_awaited( &self, task: impl Future<Output = ()> + Send + 'static ) -> Self::Handle
+	{
+		self.spawn( task )
+	}
+}
+
+
+
+#[ cfg( test ) ]
+//
+mod tests
+{
+	#[ allow( unused_imports ) ] // false positive
+	//
+	use super::*;
+	use crate::SmolTp;
+
+	// Every forwarding mode should terminate on a multithreaded executor.
+	//
+	#[test]
+	//
+	fn all_forward_modes()
+	{
+		let exec = SmolTp::new( 2 );
+
+		for forward in Forward::ALL.iter()
+		{
+			let mut ring = SmolNativeRing::new( 10, *forward );
+
+			exec.block_on( ring.run( exec.executor().clone() ) );
+		}
+	}
+}
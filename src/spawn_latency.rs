@@ -0,0 +1,146 @@
+//! Measure how long it takes between calling spawn and the first poll of the spawned future,
+//! through each of the spawn traits. The latencies are recorded with
+//! [`record_latencies`](crate::record_latencies) at the end of the run.
+//!
+//! Tasks are spawned one at a time from the future passed to `block_on`, and the next one is only
+//! spawned when the previous one has reported, so every spawn finds an idle executor. On the single
+//! threaded executors this measures how long it takes to get from the spawning task to the new one,
+//! on the multithreaded ones, how long it takes a worker to pick up a task spawned from outside.
+//!
+use futures::{ channel::oneshot, task::{ LocalSpawn, LocalSpawnExt, Spawn, SpawnExt } };
+use std::time::{ Duration, Instant };
+use async_executors::*;
+use crate::record_latencies;
+
+
+pub struct SpawnLatency
+{
+	spawns: usize,
+}
+
+
+impl SpawnLatency
+{
+	// Measure `spawns` spawns per run.
+	//
+	pub fn new( spawns: usize ) -> Self
+	{
+		assert!( spawns > 0 );
+
+		Self { spawns }
+	}
+
+
+	// Measure `Spawn::spawn`. The task reports back over a oneshot channel.
+	//
+	pub async fn run( &self, exec: impl Spawn )
+	{
+		let mut latencies = Vec::with_capacity( self.spawns );
+
+		for _ in 0..self.spawns
+		{
+			let (tx, rx) = oneshot::channel();
+			let start    = Instant::now();
+
+			exec.spawn( async move { tx.send( start.elapsed() ).expect( "send latency" ); } ).expect( "spawn task" );
+
+			latencies.push( rx.await.expect( "receive latency" ) );
+		}
+
+		record( latencies );
+	}
+
+
+	// Measure `LocalSpawn::spawn_local`. The task reports back over a oneshot channel.
+	//
+	pub async fn run_local( &self, exec: impl LocalSpawn )
+	{
+		let mut latencies = Vec::with_capacity( self.spawns );
+
+		for _ in 0..self.spawns
+		{
+			let (tx, rx) = oneshot::channel();
+			let start    = Instant::now();
+
+			exec.spawn_local( async move { tx.send( start.elapsed() ).expect( "send latency" ); } ).expect( "spawn task" );
+
+			latencies.push( rx.await.expect( "receive latency" ) );
+		}
+
+		record( latencies );
+	}
+
+
+	// Measure `SpawnHandle::spawn_handle`. The task returns it's latency through the JoinHandle.
+	//
+	pub async fn run_handle( &self, exec: impl SpawnHandle<Duration> )
+	{
+		let mut latencies = Vec::with_capacity( self.spawns );
+
+		for _ in 0..self.spawns
+		{
+			let start = Instant::now();
+
+			latencies.push( exec.spawn_handle( async move { start.elapsed() } ).expect( "spawn task" ).await );
+		}
+
+		record( latencies );
+	}
+
+
+	// Measure `LocalSpawnHandle::spawn_handle_local`. The task returns it's latency through the JoinHandle.
+	//
+	pub async fn run_local_handle( &self, exec: impl LocalSpawnHandle<Duration> )
+	{
+		let mut latencies = Vec::with_capacity( self.spawns );
+
+		for _ in 0..self.spawns
+		{
+			let start = Instant::now();
+
+			latencies.push( exec.spawn_handle_local( async move { start.elapsed() } ).expect( "spawn task" ).await );
+		}
+
+		record( latencies );
+	}
+}
+
+
+fn record( latencies: Vec<Duration> )
+{
+	record_latencies( latencies.into_iter().map( |l| l.as_nanos() as u64 ) );
+}
+
+
+
+#[ cfg( test ) ]
+//
+mod tests
+{
+	use super::*;
+	use crate::{ LATENCY_TESTS, take_latencies };
+	use futures::executor::{ LocalPool, ThreadPool, block_on };
+
+	// The handle variants aren't tested, like the handle rings, since they depend on async_executors.
+	//
+	#[test]
+	//
+	fn every_spawn_measured()
+	{
+		let _lock = LATENCY_TESTS.lock();
+
+		let pool  = ThreadPool::new().expect( "create threadpool" );
+		let bench = SpawnLatency::new( 50 );
+
+		take_latencies();
+		block_on( bench.run( pool ) );
+		assert_eq!( 50, take_latencies().expect( "latencies recorded" ).len() );
+
+
+		let mut local   = LocalPool::new();
+		let     spawner = local.spawner();
+
+		local.run_until( bench.run_local( spawner ) );
+		assert_eq!( 50, take_latencies().expect( "latencies recorded" ).len() );
+	}
+}
@@ -0,0 +1,114 @@
+//! Summary statistics of the samples of a benchmark.
+//!
+use serde::{ Deserialize, Serialize };
+use std::time::Duration;
+
+
+// All times are in nanoseconds. Percentiles use the nearest rank method.
+//
+#[ derive( Clone, Debug, PartialEq, Serialize, Deserialize ) ]
+//
+pub struct Stats
+{
+	pub count    : usize ,
+	pub mean_ns  : f64   ,
+	pub median_ns: f64   ,
+	pub stddev_ns: f64   ,
+	pub min_ns   : f64   ,
+	pub p90_ns   : f64   ,
+	pub p95_ns   : f64   ,
+	pub p99_ns   : f64   ,
+	pub max_ns   : f64   ,
+}
+
+
+impl Stats
+{
+	pub fn new( samples: &[Duration] ) -> Self
+	{
+		let mut sorted: Vec<f64> = samples.iter().map( |s| s.as_nanos() as f64 ).collect();
+
+		sorted.sort_by( |a, b| a.partial_cmp( b ).expect( "durations are not NaN" ) );
+
+		let count = sorted.len();
+
+		if count == 0
+		{
+			return Self { count, mean_ns: 0.0, median_ns: 0.0, stddev_ns: 0.0, min_ns: 0.0, p90_ns: 0.0, p95_ns: 0.0, p99_ns: 0.0, max_ns: 0.0 };
+		}
+
+		let mean = sorted.iter().sum::<f64>() / count as f64;
+
+		// The sample standard deviation.
+		//
+		let stddev = match count
+		{
+			1 => 0.0,
+			_ => ( sorted.iter().map( |s| ( s - mean ).powi( 2 ) ).sum::<f64>() / ( count - 1 ) as f64 ).sqrt(),
+		};
+
+		let median = match count % 2
+		{
+			0 => ( sorted[ count/2 - 1 ] + sorted[ count/2 ] ) / 2.0,
+			_ => sorted[ count/2 ],
+		};
+
+		Self
+		{
+			count                                   ,
+			mean_ns  : mean                         ,
+			median_ns: median                       ,
+			stddev_ns: stddev                       ,
+			min_ns   : sorted[0]                    ,
+			p90_ns   : percentile( &sorted, 90.0 )  ,
+			p95_ns   : percentile( &sorted, 95.0 )  ,
+			p99_ns   : percentile( &sorted, 99.0 )  ,
+			max_ns   : sorted[ count - 1 ]          ,
+		}
+	}
+}
+
+
+// The value below which `p` percent of the sorted samples fall.
+//
+fn percentile( sorted: &[f64], p: f64 ) -> f64
+{
+	let rank = ( p / 100.0 * sorted.len() as f64 ).ceil() as usize;
+
+	sorted[ rank.max( 1 ) - 1 ]
+}
+
+
+
+#[ cfg( test ) ]
+//
+mod tests
+{
+	use super::*;
+
+	#[test]
+	//
+	fn stats()
+	{
+		let samples: Vec<Duration> = ( 1..=100 ).map( Duration::from_nanos ).collect();
+		let stats = Stats::new( &samples );
+
+		assert_eq!( 100  , stats.count     );
+		assert_eq!( 50.5 , stats.mean_ns   );
+		assert_eq!( 50.5 , stats.median_ns );
+		assert_eq!( 1.0  , stats.min_ns    );
+		assert_eq!( 90.0 , stats.p90_ns    );
+		assert_eq!( 99.0 , stats.p99_ns    );
+		assert_eq!( 100.0, stats.max_ns    );
+
+		assert!( ( stats.stddev_ns - 29.011 ).abs() < 0.001 );
+	}
+
+
+	#[test]
+	//
+	fn empty()
+	{
+		assert_eq!( 0, Stats::new( &[] ).count );
+	}
+}
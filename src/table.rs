@@ -0,0 +1,184 @@
+//! Render results as Markdown comparison tables, to paste in the README or a release note.
+//!
+use crate::Summary;
+use std::fmt::Write;
+
+
+// Render one table per group and set of parameters other than `column`. Every routine gets a row,
+// every value of the `column` parameter a column. Cells show the mean time and the ratio to the
+// fastest routine in that column, which is shown in bold.
+//
+pub fn markdown( summaries: &[Summary], column: &str ) -> String
+{
+	let mut tables: Vec<Table<'_>> = Vec::new();
+
+	for s in summaries
+	{
+		let others: Vec<( String, String )> = s.id.params.iter().filter( |(n, _)| n != column ).cloned().collect();
+
+		match tables.iter_mut().find( |t| t.group == s.id.group && t.others == others )
+		{
+			Some( table ) => table.summaries.push( s ),
+			None          => tables.push( Table { group: &s.id.group, others, summaries: vec![ s ] } ),
+		}
+	}
+
+	let mut out = String::new();
+
+	for table in tables
+	{
+		table.render( column, &mut out );
+	}
+
+	out
+}
+
+
+struct Table<'a>
+{
+	group    : &'a str                 ,
+	others   : Vec<( String, String )> ,
+	summaries: Vec<&'a Summary>        ,
+}
+
+
+impl Table<'_>
+{
+	fn render( &self, column: &str, out: &mut String )
+	{
+		let value = |s: &Summary| s.id.get( column ).unwrap_or( "-" ).to_string();
+
+		let mut rows   : Vec<( &str, &str )> = Vec::new();
+		let mut columns: Vec<String>         = Vec::new();
+
+		for s in &self.summaries
+		{
+			let row = ( s.id.executor.as_str(), s.id.workload.as_str() );
+
+			if !rows   .contains( &row        ) { rows   .push( row        ); }
+			if !columns.contains( &value( s ) ) { columns.push( value( s ) ); }
+		}
+
+		// Sort numerically when possible, so 100 comes after 20.
+		//
+		if columns.iter().all( |c| c.parse::<f64>().is_ok() )
+		{
+			columns.sort_by( |a, b| a.parse::<f64>().unwrap().partial_cmp( &b.parse::<f64>().unwrap() ).expect( "not NaN" ) );
+		}
+
+
+		let params: Vec<String> = self.others.iter().map( |(n, v)| format!( "{}: {}", n, v ) ).collect();
+
+		let title = match params.is_empty()
+		{
+			true  => self.group.to_string(),
+			false => format!( "{} ({})", self.group, params.join( ", " ) ),
+		};
+
+		writeln!( out, "### {}\n", title ).expect( "write to string" );
+
+
+		let header: Vec<String> = columns.iter().map( |c| format!( "{} {}", c, column ) ).collect();
+
+		writeln!( out, "| Executor | Workload | {} |", header.join( " | " ) ).expect( "write to string" );
+		writeln!( out, "|---|---|{}", "---:|".repeat( columns.len() ) ).expect( "write to string" );
+
+
+		let mean = |row: ( &str, &str ), col: &str| -> Option<f64>
+		{
+			self.summaries.iter()
+
+				.find( |s| ( s.id.executor.as_str(), s.id.workload.as_str() ) == row && value( s ) == col )
+				.map ( |s| s.stats.mean_ns )
+		};
+
+		let fastest: Vec<Option<f64>> = columns.iter().map( |col|
+		{
+			rows.iter().filter_map( |row| mean( *row, col ) ).min_by( |a, b| a.partial_cmp( b ).expect( "not NaN" ) )
+
+		}).collect();
+
+
+		for row in &rows
+		{
+			let cells: Vec<String> = columns.iter().zip( &fastest ).map( |(col, fastest)|
+			{
+				match ( mean( *row, col ), fastest )
+				{
+					( Some( m ), Some( f ) ) if m == *f => format!( "**{}** (1.00x)", format_ns( m ) ),
+					( Some( m ), Some( f ) )            => format!( "{} ({:.2}x)", format_ns( m ), m / f ),
+					_                                   => "-".to_string(),
+				}
+
+			}).collect();
+
+			writeln!( out, "| {} | {} | {} |", row.0, row.1, cells.join( " | " ) ).expect( "write to string" );
+		}
+
+		out.push( '\n' );
+	}
+}
+
+
+// Format a time with a unit that keeps the number readable.
+//
+pub fn format_ns( ns: f64 ) -> String
+{
+	match ns
+	{
+		ns if ns < 1e3 => format!( "{:.0} ns", ns       ),
+		ns if ns < 1e6 => format!( "{:.1} µs", ns / 1e3 ),
+		ns if ns < 1e9 => format!( "{:.2} ms", ns / 1e6 ),
+		ns             => format!( "{:.2} s" , ns / 1e9 ),
+	}
+}
+
+
+
+#[ cfg( test ) ]
+//
+mod tests
+{
+	use super::*;
+	use crate::{ BenchId, Record };
+	use std::time::Duration;
+
+	fn summary( executor: &str, forward: &str, nodes: usize, us: u64 ) -> Summary
+	{
+		let id = BenchId::new( "Ring", executor, "BoundedRing" ).param( "forward", forward ).param( "nodes", nodes );
+
+		Summary::new( &Record::new( id, vec![ Duration::from_micros( us ) ] ) )
+	}
+
+
+	#[test]
+	//
+	fn table()
+	{
+		let summaries = vec!
+		[
+			summary( "ThreadPool", "detach", 100, 300 ),
+			summary( "TokioTp"   , "detach", 100, 200 ),
+			summary( "ThreadPool", "detach", 20 , 30  ),
+			summary( "TokioTp"   , "await" , 20 , 10  ),
+		];
+
+		let expect = "\
+### Ring (forward: detach)
+
+| Executor | Workload | 20 nodes | 100 nodes |
+|---|---|---:|---:|
+| ThreadPool | BoundedRing | **30.0 µs** (1.00x) | 300.0 µs (1.50x) |
+| TokioTp | BoundedRing | - | **200.0 µs** (1.00x) |
+
+### Ring (forward: await)
+
+| Executor | Workload | 20 nodes |
+|---|---|---:|
+| TokioTp | BoundedRing | **10.0 µs** (1.00x) |
+
+";
+
+		assert_eq!( expect, markdown( &summaries, "nodes" ) );
+	}
+}
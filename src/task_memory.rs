@@ -0,0 +1,346 @@
+//! The memory footprint of idle tasks: spawn a number of tasks that wait on a channel and see how
+//! much the memory of the process grows.
+//!
+//! The growth of the resident set size comes from `/proc/self/status`, so it is only available on
+//! Linux. The allocator usually keeps the memory of the tasks of one iteration around for the next,
+//! so only the biggest growth of all iterations is kept, which is normally that of the first one.
+//! That is why `timed` also keeps what was recorded by the warm up.
+//!
+//! With the `count_alloc` feature, the growth of the bytes allocated on the heap is recorded too.
+//! That is exact for every iteration and includes the channels the tasks wait on.
+//!
+use crate::{ Record, live_bytes };
+use futures::{ channel::oneshot, future::poll_fn, task::{ AtomicWaker, Spawn, SpawnExt } };
+use serde::{ Deserialize, Serialize };
+use std::{ fmt, fs, sync::{ Arc, Mutex, atomic::{ AtomicUsize, Ordering::SeqCst } }, task::Poll };
+
+
+static MEMORY: Mutex< Option<TaskMemory> > = Mutex::new( None );
+
+
+// The memory growth caused by spawning idle tasks. All sizes are in bytes.
+//
+#[ derive( Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize ) ]
+//
+pub struct TaskMemory
+{
+	pub tasks: u64,
+
+	// Growth of the resident set size.
+	//
+	pub rss: u64,
+
+	// Growth of the bytes allocated on the heap, with the `count_alloc` feature.
+	//
+	pub heap: Option<u64>,
+
+	// The peak resident set size of the process between the start of the spawning and the moment
+	// all tasks are idle. 0 if the peak can't be reset, see `reset_peak_rss`.
+	//
+	pub peak_rss: u64,
+}
+
+
+impl TaskMemory
+{
+	pub fn rss_per_task( &self ) -> f64
+	{
+		self.rss as f64 / self.tasks.max( 1 ) as f64
+	}
+
+
+	pub fn heap_per_task( &self ) -> Option<f64>
+	{
+		self.heap.map( |h| h as f64 / self.tasks.max( 1 ) as f64 )
+	}
+
+
+	// Keep the biggest growth of both.
+	//
+	pub fn merge( &mut self, other: TaskMemory )
+	{
+		self.rss      = self.rss     .max( other.rss      );
+		self.heap     = self.heap    .max( other.heap     );
+		self.peak_rss = self.peak_rss.max( other.peak_rss );
+	}
+}
+
+
+// Record the memory growth of the workload that is running.
+//
+pub fn record_task_memory( memory: TaskMemory )
+{
+	let mut guard = MEMORY.lock().expect( "lock task memory" );
+
+	match &mut *guard
+	{
+		Some( m ) => m.merge( memory ),
+		None      => *guard = Some( memory ),
+	}
+}
+
+
+// Take the memory growth recorded since the last call.
+//
+pub fn take_task_memory() -> Option<TaskMemory>
+{
+	MEMORY.lock().expect( "lock task memory" ).take()
+}
+
+
+// The resident set size of the process in bytes. `None` if `/proc/self/status` can't be read.
+//
+pub fn rss() -> Option<u64>
+{
+	status_bytes( "VmRSS:" )
+}
+
+
+// The peak resident set size of the process in bytes since it started or since the last
+// `reset_peak_rss`. `None` if `/proc/self/status` can't be read.
+//
+pub fn peak_rss() -> Option<u64>
+{
+	status_bytes( "VmHWM:" )
+}
+
+
+// Reset the peak resident set size to the current one, by writing 5 to `/proc/self/clear_refs`
+// (Linux 4.0 and later). Returns false if that failed.
+//
+pub fn reset_peak_rss() -> bool
+{
+	fs::write( "/proc/self/clear_refs", "5" ).is_ok()
+}
+
+
+// Read a field of `/proc/self/status` that is in kB, eg. `VmRSS:     4300 kB`.
+//
+fn status_bytes( field: &str ) -> Option<u64>
+{
+	parse_status( &fs::read_to_string( "/proc/self/status" ).ok()?, field )
+}
+
+
+// Parse a field that is in kB out of the contents of `/proc/self/status`.
+//
+fn parse_status( status: &str, field: &str ) -> Option<u64>
+{
+	let line = status.lines().find( |l| l.starts_with( field ) )?;
+	let kb   = line[ field.len().. ].trim().trim_end_matches( "kB" ).trim().parse::<u64>().ok()?;
+
+	Some( kb * 1024 )
+}
+
+
+
+// Spawn tasks that wait on a oneshot channel until all of them have been polled, measure the
+// memory, then let them finish.
+//
+pub struct IdleTasks
+{
+	tasks: usize,
+}
+
+
+impl IdleTasks
+{
+	pub fn new( tasks: usize ) -> Self
+	{
+		assert!( tasks > 0 );
+
+		Self { tasks }
+	}
+
+
+	pub async fn run( &self, exec: impl Spawn )
+	{
+		let parked  = Arc::new( Parked::default() );
+		let mut txs = Vec::with_capacity( self.tasks );
+
+		let rss_before  = rss();
+		let heap_before = live_bytes();
+		let reset       = reset_peak_rss();
+
+		for _ in 0..self.tasks
+		{
+			let (tx, rx) = oneshot::channel::<()>();
+			let parked   = parked.clone();
+
+			txs.push( tx );
+
+			exec.spawn( async move
+			{
+				parked.start();
+
+				// Wait for the sender to be dropped.
+				//
+				let _ = rx.await;
+
+				parked.finish();
+
+			}).expect( "spawn idle task" );
+		}
+
+		parked.until( self.tasks ).await;
+
+		let rss_after  = rss();
+		let heap_after = live_bytes();
+		let peak       = if reset { peak_rss() } else { None };
+
+		drop( txs );
+
+		// Wait for all tasks to finish, so they don't outlive the run.
+		//
+		parked.until( 0 ).await;
+
+		if let ( Some( before ), Some( after ) ) = ( rss_before, rss_after )
+		{
+			record_task_memory( TaskMemory
+			{
+				tasks   : self.tasks as u64                                                   ,
+				rss     : after.saturating_sub( before )                                      ,
+				heap    : heap_after.zip( heap_before ).map( |(a, b)| a.saturating_sub( b ) ) ,
+				peak_rss: peak.unwrap_or_default()                                            ,
+			});
+		}
+	}
+}
+
+
+// Counts the tasks that are running and wakes the run when the count it waits for is reached.
+//
+#[ derive( Default ) ]
+//
+struct Parked
+{
+	count: AtomicUsize ,
+	waker: AtomicWaker ,
+}
+
+
+impl Parked
+{
+	fn start( &self )
+	{
+		self.count.fetch_add( 1, SeqCst );
+		self.waker.wake();
+	}
+
+
+	fn finish( &self )
+	{
+		self.count.fetch_sub( 1, SeqCst );
+		self.waker.wake();
+	}
+
+
+	async fn until( &self, count: usize )
+	{
+		poll_fn( |cx|
+		{
+			self.waker.register( cx.waker() );
+
+			match self.count.load( SeqCst ) == count
+			{
+				true  => Poll::Ready(()),
+				false => Poll::Pending,
+			}
+
+		}).await
+	}
+}
+
+
+
+// Prints a table of the memory per task in a set of records. Records without task memory are skipped.
+//
+pub struct MemoryReport( pub Vec<( String, TaskMemory )> );
+
+
+impl MemoryReport
+{
+	pub fn new( records: &[Record] ) -> Self
+	{
+		Self( records.iter().filter_map( |r| Some(( r.id.to_string(), r.memory? )) ).collect() )
+	}
+
+
+	pub fn is_empty( &self ) -> bool
+	{
+		self.0.is_empty()
+	}
+}
+
+
+impl fmt::Display for MemoryReport
+{
+	fn fmt( &self, f: &mut fmt::Formatter<'_> ) -> fmt::Result
+	{
+		let width = self.0.iter().map( |(id, _)| id.len() ).max().unwrap_or( 0 );
+
+		writeln!( f, "Memory per idle task (bytes):" )?;
+		writeln!( f )?;
+		writeln!( f, "  {:<width$}  {:>10}  {:>10}  {:>10}  {:>14}", "", "tasks", "rss", "heap", "peak rss", width = width )?;
+
+		for (id, m) in &self.0
+		{
+			let heap = m.heap_per_task().map( |h| format!( "{:.0}", h ) ).unwrap_or_else( || "-".to_string() );
+
+			writeln!
+			(
+				f, "  {:<width$}  {:>10}  {:>10.0}  {:>10}  {:>14}",
+				id, m.tasks, m.rss_per_task(), heap, m.peak_rss, width = width,
+			)?;
+		}
+
+		Ok(())
+	}
+}
+
+
+
+#[ cfg( test ) ]
+//
+mod tests
+{
+	use super::*;
+	use futures::executor::{ LocalPool, ThreadPool, block_on };
+
+	#[test]
+	//
+	fn status()
+	{
+		if cfg!( target_os = "linux" )
+		{
+			assert!( rss().expect( "read rss" ) > 0 );
+
+			// Other tests allocate while this runs, so compare both from the same snapshot.
+			//
+			let status = fs::read_to_string( "/proc/self/status" ).expect( "read /proc/self/status" );
+			let rss    = parse_status( &status, "VmRSS:" ).expect( "parse rss"      );
+			let peak   = parse_status( &status, "VmHWM:" ).expect( "parse peak rss" );
+
+			assert!( peak >= rss );
+		}
+	}
+
+
+	// The run waits for all tasks to be parked and then to finish, so this would hang if it
+	// miscounted.
+	//
+	#[test]
+	//
+	fn tasks_finish()
+	{
+		let pool = ThreadPool::new().expect( "create threadpool" );
+
+		block_on( IdleTasks::new( 100 ).run( pool ) );
+
+
+		let mut local   = LocalPool::new();
+		let     spawner = local.spawner();
+
+		local.run_until( IdleTasks::new( 100 ).run( spawner ) );
+	}
+}
@@ -0,0 +1,94 @@
+//! This uses bounded channels and has the possibility to spawn each message forwarding on the executor
+//! rather than awaiting. See [`Forward`](crate::Forward).
+//!
+//! Only uses the inherent API of the pool, `spawn_ok`, not the `Spawn` trait.
+//!
+use futures::{ channel::mpsc, executor::ThreadPool, future::{ FutureExt, RemoteHandle } };
+use std::future::Future;
+use crate::{ Forward, Message, Node, Ring, RingConfig, RingSpawn };
+
+
+pub struct ThreadPoolNativeRing( Ring );
+
+
+impl ThreadPoolNativeRing
+{
+	// Create channels between all the nodes.
+	//
+	pub fn new( n: usize, forward: Forward ) -> Self
+	{
+		Self::with_config( RingConfig::new( n, forward ) )
+	}
+
+
+	// Create a ring with a custom channel capacity and number of laps.
+	//
+	pub fn with_config( config: RingConfig ) -> Self
+	{
+		Self( Ring::with_config( config ) )
+	}
+
+
+	// Run the benchmark.
+	//
+	pub async fn run( &mut self, exec: ThreadPool )
+	{
+		self.0.run( exec ).await;
+	}
+}
+
+
+
+// The handle of an awaited task comes from `remote_handle`, the pool only spawns it.
+//
+impl RingSpawn for ThreadPool
+{
+	type Handle = RemoteHandle<()>;
+
+	fn spawn_node<M: Message>( &self, node: Node<M>, done: mpsc::Sender<()> )
+	{
+		self.spawn_ok( node.run( self.clone(), done ) );
+	}
+
+	fn detach( &self, task: impl Future<Output = ()> + Send + 'static )
+	{
+		self.spawn_ok( task );
+	}
+
+	fn spawn_awaited( &self, task: impl Future<Output = ()> + Send + 'static ) -> Self::Handle
+	{
+		let (task, handle) = task.remote_handle();
+
+		self.spawn_ok( task );
+
+		handle
+	}
+}
+
+
+
+#[ cfg( test ) ]
+//
+mod tests
+{
+	#[ allow( unused_imports ) ] // false positive
+	//
+	use super::*;
+	use futures::executor::block_on;
+
+	// Every forwarding mode should terminate on a multithreaded executor.
+	//
+	#[test]
+	//
+	fn all_forward_modes()
+	{
+		let pool = ThreadPool::new().expect( "create threadpool" );
+
+		for forward in Forward::ALL.iter()
+		{
+			let mut ring = ThreadPoolNativeRing::new( 10, *forward );
+
+			block_on( ring.run( pool.clone() ) );
+		}
+	}
+}
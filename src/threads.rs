@@ -0,0 +1,111 @@
+//! Helpers to sweep the number of worker threads of the multithreaded executors.
+//!
+use crate::Pinning;
+use std::{ env, sync::Mutex };
+
+
+// The number of worker threads of the async-std runtime, once `start_async_std` started it.
+//
+static ASYNC_STD_STARTED: Mutex< Option<usize> > = Mutex::new( None );
+
+
+// The worker thread counts to benchmark: powers of two up to the number of available cores,
+// followed by the number of cores itself.
+//
+pub fn thread_counts() -> Vec<usize>
+{
+	let cores      = num_cpus::get();
+	let mut counts = Vec::new();
+	let mut n      = 1;
+
+	while n < cores
+	{
+		counts.push( n );
+		n *= 2;
+	}
+
+	counts.push( cores );
+
+	counts
+}
+
+
+// async-std runs on a global runtime which reads the `ASYNC_STD_THREAD_COUNT` environment variable
+// once, when it is first used. It can not be resized from within the process, so in order to sweep
+// it, run the benchmark several times with a different value for the variable.
+//
+// This returns the number of threads the global runtime uses, or will use if it didn't start yet.
+//
+pub fn async_std_threads() -> usize
+{
+	async_std_started().unwrap_or_else( env_threads )
+}
+
+
+// The number of worker threads of the async-std runtime, `None` if `start_async_std` didn't run yet.
+//
+pub fn async_std_started() -> Option<usize>
+{
+	*ASYNC_STD_STARTED.lock().expect( "lock async-std threads" )
+}
+
+
+// Start the async-std runtime now rather than when it is first used, and do it `unpinned`, so it's
+// worker threads don't inherit the core of the pinned driver thread. Returns the number of worker
+// threads it runs.
+//
+// Only the first call starts it, with `threads` workers if set. Later calls return the count it
+// started with, whatever they ask for. Anything that uses async-std before the first call starts
+// it with the value the environment had at that moment, which this can't see.
+//
+pub fn start_async_std( threads: Option<usize>, pinning: &Pinning ) -> usize
+{
+	let mut started = ASYNC_STD_STARTED.lock().expect( "lock async-std threads" );
+
+	if let Some( threads ) = *started { return threads; }
+
+	if let Some( threads ) = threads
+	{
+		env::set_var( "ASYNC_STD_THREAD_COUNT", threads.to_string() );
+	}
+
+	pinning.unpinned( || drop( async_std::task::spawn( async {} ) ) );
+
+	let threads = env_threads();
+
+	*started = Some( threads );
+
+	threads
+}
+
+
+// What `ASYNC_STD_THREAD_COUNT` asks for, or the number of cores.
+//
+fn env_threads() -> usize
+{
+	env::var( "ASYNC_STD_THREAD_COUNT" ).ok()
+
+		.and_then( |count| count.parse().ok() )
+		.unwrap_or_else( num_cpus::get )
+}
+
+
+
+#[ cfg( test ) ]
+//
+mod tests
+{
+	use super::*;
+
+	#[test]
+	//
+	fn counts_go_up_to_cores()
+	{
+		let counts = thread_counts();
+
+		assert_eq!( Some( &1              ), counts.first() );
+		assert_eq!( Some( &num_cpus::get() ), counts.last()  );
+
+		assert!( counts.windows( 2 ).all( |w| w[0] < w[1] ) );
+	}
+}
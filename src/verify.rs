@@ -0,0 +1,294 @@
+//! Check that a ring run is correct, not only fast. Enable it with `RingConfig::verify` or the
+//! `--verify` flag of the runner.
+//!
+//! Every node starts a message with value 1 and every node it passes increments it, so node `id`
+//! receives the message of node `origin` with the values `v` for which `origin + v ≡ id (mod n)`.
+//! Over all origins, a node thus receives every value from 1 to `end` exactly once, and the values
+//! from one origin come `n` apart, in increasing order. The check verifies that for every message a
+//! node receives, and at the end of the run that every node received the last value of every
+//! origin, ie. that every message completed it's laps.
+//!
+//! It also counts the tasks the ring spawns. At the end of the run, it waits for all of them to
+//! finish, so no task outlives the run. If one never finishes, the run hangs rather than reporting
+//! a result.
+//!
+//! A failed check doesn't panic in the node that found it. On a multithreaded executor that would
+//! only end the task of that node, and the others would wait for it forever. The first failure is
+//! kept and the run panics with it at the end, on the thread that drives it.
+//!
+//! The checks lock a mutex for every message, so don't compare timings of verified runs to others.
+//!
+use crate::RingConfig;
+use futures::{ future::poll_fn, task::AtomicWaker };
+use std::{ sync::{ Arc, Mutex, atomic::{ AtomicUsize, Ordering::SeqCst } }, task::Poll };
+
+
+pub struct RingCheck
+{
+	n  : usize,
+	end: usize,
+
+	// For every node, the last value received from every origin, indexed by value modulo n.
+	//
+	received: Vec< Mutex< Vec<usize> > >,
+
+	// The tasks of the ring that are still running, and the run waiting for them.
+	//
+	live: AtomicUsize,
+	idle: AtomicWaker,
+
+	// The first check that failed.
+	//
+	failure: Mutex< Option<String> >,
+}
+
+
+impl RingCheck
+{
+	// `None` if the config doesn't ask for verification.
+	//
+	pub fn new( config: &RingConfig ) -> Option< Arc<Self> >
+	{
+		if !config.verify { return None; }
+
+		let n = config.nodes;
+
+		Some( Arc::new( Self
+		{
+			n                                                                  ,
+			end     : config.end()                                             ,
+			received: ( 0..n ).map( |_| Mutex::new( vec![ 0; n ] ) ).collect() ,
+			live    : AtomicUsize::new( 0 )                                    ,
+			idle    : AtomicWaker::new()                                       ,
+			failure : Mutex::new( None )                                       ,
+		}))
+	}
+
+
+	// Node `id` (starting at 1) received `msg`. Fails the run if it's not the next value from the
+	// origin it came from.
+	//
+	pub fn receive( &self, id: usize, msg: usize )
+	{
+		if msg < 1 || msg > self.end
+		{
+			return self.fail( format!( "node {} received {}, which is out of range 1..={}", id, msg, self.end ) );
+		}
+
+		let     origin   = msg % self.n;
+		let mut received = self.received[ id - 1 ].lock().expect( "lock received" );
+
+		let expected = match received[ origin ]
+		{
+			0    => first_value( origin, self.n ),
+			last => last + self.n,
+		};
+
+		if msg != expected
+		{
+			self.fail( format!( "node {} received {}, expected {}: a message was lost, duplicated or reordered", id, msg, expected ) );
+		}
+
+		received[ origin ] = msg;
+	}
+
+
+	// Keep the first failure, the ones after it are usually caused by it.
+	//
+	fn fail( &self, failure: String )
+	{
+		self.failure.lock().expect( "lock failure" ).get_or_insert( failure );
+	}
+
+
+	// Count a task of the ring until the returned guard is dropped.
+	//
+	pub fn task( self: &Arc<Self> ) -> RingTask
+	{
+		self.live.fetch_add( 1, SeqCst );
+
+		RingTask( self.clone() )
+	}
+
+
+	// Wait for all tasks of the ring to finish, then check that every message completed it's laps.
+	// Panics with the first check that failed.
+	//
+	pub async fn finished( &self )
+	{
+		poll_fn( |cx|
+		{
+			self.idle.register( cx.waker() );
+
+			match self.live.load( SeqCst )
+			{
+				0 => Poll::Ready(()),
+				_ => Poll::Pending,
+			}
+
+		}).await;
+
+		if let Some( failure ) = self.failure.lock().expect( "lock failure" ).take()
+		{
+			panic!( "{}", failure );
+		}
+
+		for (i, received) in self.received.iter().enumerate()
+		{
+			let received = received.lock().expect( "lock received" );
+
+			for origin in 0..self.n
+			{
+				let last = last_value( origin, self.n, self.end );
+
+				assert_eq!
+				(
+					last, received[ origin ],
+					"node {} never received {}: a message did not complete it's laps", i + 1, last,
+				);
+			}
+		}
+	}
+}
+
+
+// The first value a node receives from an origin, by value modulo n.
+//
+fn first_value( origin: usize, n: usize ) -> usize
+{
+	match origin
+	{
+		0 => n,
+		o => o,
+	}
+}
+
+
+// The last value a node receives from an origin, by value modulo n, on the last lap.
+//
+fn last_value( origin: usize, n: usize, end: usize ) -> usize
+{
+	end - n + first_value( origin, n )
+}
+
+
+
+// Keeps a task of the ring counted while it lives.
+//
+pub struct RingTask( Arc<RingCheck> );
+
+
+impl Drop for RingTask
+{
+	fn drop( &mut self )
+	{
+		if self.0.live.fetch_sub( 1, SeqCst ) == 1
+		{
+			self.0.idle.wake();
+		}
+	}
+}
+
+
+
+#[ cfg( test ) ]
+//
+mod tests
+{
+	use super::*;
+	use crate::{ BoundedRing, Forward, LocalRing };
+	use futures::executor::{ LocalPool, ThreadPool, block_on };
+
+	fn check( nodes: usize, laps: usize ) -> Arc<RingCheck>
+	{
+		RingCheck::new( &RingConfig::new( nodes, Forward::Inline ).laps( laps ).verify( true ) ).expect( "verify" )
+	}
+
+
+	// Feed a check what a correct ring of 3 nodes and 2 laps delivers to node 1: from node 3 the
+	// values 1 and 4, from node 2 the values 2 and 5, and it's own message as 3 and 6.
+	//
+	#[test]
+	//
+	fn correct_order()
+	{
+		let check = check( 3, 2 );
+
+		for node in 1..=3
+		{
+			for msg in 1..=6 { check.receive( node, msg ); }
+		}
+
+		block_on( check.finished() );
+	}
+
+
+	// Node 1 gets the message of node 3 on it's second lap, without having seen it on the first.
+	//
+	#[test]
+	//
+	#[ should_panic( expected = "reordered" ) ]
+	//
+	fn skipped_message()
+	{
+		let check = check( 3, 2 );
+
+		check.receive( 1, 4 );
+
+		block_on( check.finished() );
+	}
+
+
+	// A failure in a task on another thread ends up on the thread that waits for the run.
+	//
+	#[test]
+	//
+	#[ should_panic( expected = "out of range" ) ]
+	//
+	fn failure_in_task()
+	{
+		let pool  = ThreadPool::new().expect( "create threadpool" );
+		let check = check( 3, 2 );
+		let guard = check.task();
+		let node  = check.clone();
+
+		pool.spawn_ok( async move { let _guard = guard; node.receive( 1, 7 ); } );
+
+		block_on( check.finished() );
+	}
+
+
+	#[test]
+	//
+	#[ should_panic( expected = "did not complete" ) ]
+	//
+	fn lost_message()
+	{
+		let check = check( 3, 2 );
+
+		for msg in 1..=5 { check.receive( 1, msg ); }
+
+		block_on( check.finished() );
+	}
+
+
+	// Verified runs of real rings pass, in every forwarding mode.
+	//
+	#[test]
+	//
+	fn rings_verify()
+	{
+		let pool = ThreadPool::new().expect( "create threadpool" );
+
+		let mut local   = LocalPool::new();
+		let     spawner = local.spawner();
+
+		for forward in Forward::ALL.iter()
+		{
+			let config = RingConfig::new( 10, *forward ).laps( 3 ).verify( true );
+
+			block_on( BoundedRing::with_config( config ).run( pool.clone() ) );
+			local.run_until( LocalRing::with_config( config ).run( spawner.clone() ) );
+		}
+	}
+}
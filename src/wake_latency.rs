@@ -0,0 +1,228 @@
+//! Measure how long it takes from `Waker::wake` to the woken task being polled. The latencies are
+//! recorded with [`record_latencies`](crate::record_latencies) at the end of the run.
+//!
+//! A waiting task is spawned on the executor. Every time it is parked, it gets woken either by
+//! another task on the same executor (`run`) or by a thread outside of the executor (`run_remote`).
+//! On the single threaded executors, the first is a same thread wake and the second a cross thread
+//! wake, which has to unpark the executor. On the multithreaded ones the scheduler decides where the
+//! tasks run for `run`, while `run_remote` is always cross thread.
+//!
+//! The waker only wakes the task once it is parked, so every wake is a real one. The waiting task
+//! in turn wakes the waker when it parks, so neither of them has to spin.
+//!
+use futures::{ channel::oneshot, executor::block_on, task::{ Spawn, SpawnExt } };
+use std::{ future::Future, pin::Pin, sync::{ Arc, Mutex }, task::{ Context, Poll, Waker }, thread, time::{ Duration, Instant } };
+use crate::record_latencies;
+
+
+pub struct WakeLatency
+{
+	wakes: usize,
+}
+
+
+impl WakeLatency
+{
+	// Measure `wakes` wakes per run.
+	//
+	pub fn new( wakes: usize ) -> Self
+	{
+		assert!( wakes > 0 );
+
+		Self { wakes }
+	}
+
+
+	// Wake from another task on the same executor.
+	//
+	pub async fn run( &self, exec: impl Spawn )
+	{
+		let slot   = Arc::new( Mutex::new( Slot::default() ) );
+		let waiter = exec.spawn_with_handle( wait    ( slot.clone(), self.wakes ) ).expect( "spawn waiter" );
+		let waker  = exec.spawn_with_handle( wake_all( slot       , self.wakes ) ).expect( "spawn waker"  );
+
+		let latencies = waiter.await;
+
+		waker.await;
+
+		record( latencies );
+	}
+
+
+	// Wake from a thread outside of the executor.
+	//
+	pub async fn run_remote( &self, exec: impl Spawn )
+	{
+		let slot   = Arc::new( Mutex::new( Slot::default() ) );
+		let waiter = exec.spawn_with_handle( wait( slot.clone(), self.wakes ) ).expect( "spawn waiter" );
+		let wakes  = self.wakes;
+
+		let (done_tx, done_rx) = oneshot::channel();
+
+		thread::spawn( move ||
+		{
+			block_on( wake_all( slot, wakes ) );
+
+			let _ = done_tx.send(());
+		});
+
+		let latencies = waiter.await;
+
+		done_rx.await.expect( "waker thread done" );
+
+		record( latencies );
+	}
+}
+
+
+// Shared between the waiting task and the waker.
+//
+#[ derive( Default ) ]
+//
+struct Slot
+{
+	// The waiting task, set when it is parked.
+	//
+	waiter: Option<Waker>,
+
+	// The waker, set when it waits for the task to park.
+	//
+	waker: Option<Waker>,
+
+	// Set right before waking the task.
+	//
+	woken: Option<Instant>,
+}
+
+
+// Park `wakes` times and return how long each wake took.
+//
+async fn wait( slot: Arc< Mutex<Slot> >, wakes: usize ) -> Vec<Duration>
+{
+	let mut latencies = Vec::with_capacity( wakes );
+
+	for _ in 0..wakes
+	{
+		latencies.push( Woken( &slot ).await );
+	}
+
+	latencies
+}
+
+
+// Wake the waiting task `wakes` times, every time it is parked.
+//
+async fn wake_all( slot: Arc< Mutex<Slot> >, wakes: usize )
+{
+	for _ in 0..wakes
+	{
+		Parked( &slot ).await.wake();
+	}
+}
+
+
+// Resolves with the wake latency once woken through the slot.
+//
+struct Woken<'a>( &'a Mutex<Slot> );
+
+impl Future for Woken<'_>
+{
+	type Output = Duration;
+
+	fn poll( self: Pin<&mut Self>, cx: &mut Context<'_> ) -> Poll<Duration>
+	{
+		let waker =
+		{
+			let mut slot = self.0.lock().expect( "lock slot" );
+
+			if let Some( woken ) = slot.woken.take()
+			{
+				return Poll::Ready( woken.elapsed() );
+			}
+
+			slot.waiter = Some( cx.waker().clone() );
+			slot.waker.take()
+		};
+
+		// Let the waker know we are parked.
+		//
+		if let Some( waker ) = waker { waker.wake(); }
+
+		Poll::Pending
+	}
+}
+
+
+// Resolves with the waker of the waiting task once it is parked, and marks the time.
+// The caller should wake it right away.
+//
+struct Parked<'a>( &'a Mutex<Slot> );
+
+impl Future for Parked<'_>
+{
+	type Output = Waker;
+
+	fn poll( self: Pin<&mut Self>, cx: &mut Context<'_> ) -> Poll<Waker>
+	{
+		let mut slot = self.0.lock().expect( "lock slot" );
+
+		match slot.waiter.take()
+		{
+			Some( waiter ) =>
+			{
+				slot.woken = Some( Instant::now() );
+				Poll::Ready( waiter )
+			}
+
+			None =>
+			{
+				slot.waker = Some( cx.waker().clone() );
+				Poll::Pending
+			}
+		}
+	}
+}
+
+
+fn record( latencies: Vec<Duration> )
+{
+	record_latencies( latencies.into_iter().map( |l| l.as_nanos() as u64 ) );
+}
+
+
+
+#[ cfg( test ) ]
+//
+mod tests
+{
+	use super::*;
+	use crate::{ LATENCY_TESTS, take_latencies };
+	use futures::executor::{ LocalPool, ThreadPool };
+
+	#[test]
+	//
+	fn every_wake_measured()
+	{
+		let _lock = LATENCY_TESTS.lock();
+
+		let pool  = ThreadPool::new().expect( "create threadpool" );
+		let bench = WakeLatency::new( 50 );
+
+		take_latencies();
+		block_on( bench.run( pool.clone() ) );
+		assert_eq!( 50, take_latencies().expect( "latencies recorded" ).len() );
+
+		block_on( bench.run_remote( pool ) );
+		assert_eq!( 50, take_latencies().expect( "latencies recorded" ).len() );
+
+
+		let mut local   = LocalPool::new();
+		let     spawner = local.spawner();
+
+		local.run_until( bench.run( spawner.clone() ) );
+		assert_eq!( 50, take_latencies().expect( "latencies recorded" ).len() );
+
+		local.run_until( bench.run_remote( spawner ) );
+		assert_eq!( 50, take_latencies().expect( "latencies recorded" ).len() );
+	}
+}
@@ -0,0 +1,263 @@
+//! Watchdog: abort a benchmark that hangs, rather than letting it stall the whole run.
+//!
+//! `timed` arms a watch for every batch and resets it at the start of every iteration. A thread
+//! checks the watch a few times per second, and when an iteration takes longer than the timeout, it
+//! prints which benchmark hung and what the nodes of the ring were doing, then exits the process
+//! with `HUNG_EXIT_CODE`. The timeout is `BENCH_TIMEOUT` seconds, 60 by default, 0 disables it.
+//!
+//! Resetting the watch is a single atomic store, so it doesn't disturb the timings.
+//!
+//! While a watch is armed, rings also record the last message every node received in a `Progress`.
+//! A node that is far behind the others is where the ring got stuck. That is a relaxed store per
+//! message, and every node writes to it's own cache line, so the nodes don't contend with each
+//! other. Disabling the watchdog with `BENCH_TIMEOUT=0` also turns the recording off.
+//!
+use crate::{ BenchId, RingConfig };
+use std::{ env, fmt, process, sync::{ Arc, Mutex, Once, atomic::{ AtomicU64, AtomicUsize, Ordering::Relaxed } }, thread, time::{ Duration, Instant } };
+
+
+pub const TIMEOUT_VAR    : &str     = "BENCH_TIMEOUT"           ;
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs( 60 ) ;
+pub const HUNG_EXIT_CODE : i32      = 3                         ;
+
+// How often the watchdog thread checks the running iteration.
+//
+const POLL: Duration = Duration::from_millis( 100 );
+
+
+static WATCHED : Mutex< Option<Watched>       > = Mutex::new( None );
+static PROGRESS: Mutex< Option<Arc<Progress>> > = Mutex::new( None );
+
+// When the running iteration started, in nanoseconds since the batch was armed.
+//
+static ITERATION: AtomicU64 = AtomicU64::new( 0 );
+
+
+// The batch that is running.
+//
+struct Watched
+{
+	id     : BenchId  ,
+	timeout: Duration ,
+	armed  : Instant  ,
+}
+
+
+
+// Arms the watchdog for a batch of iterations of a benchmark until dropped.
+//
+pub struct Watch
+{
+	armed: Instant,
+}
+
+
+impl Watch
+{
+	// `None` if the timeout is disabled.
+	//
+	pub fn new( id: &BenchId ) -> Option<Self>
+	{
+		let timeout = timeout()?;
+
+		static START: Once = Once::new();
+
+		START.call_once( ||
+		{
+			thread::Builder::new().name( "watchdog".to_string() ).spawn( watchdog ).expect( "spawn watchdog thread" );
+		});
+
+		*PROGRESS.lock().expect( "lock progress" ) = None;
+
+		let mut watched = WATCHED.lock().expect( "lock watched" );
+		let     armed   = Instant::now();
+
+		ITERATION.store( 0, Relaxed );
+		*watched = Some( Watched { id: id.clone(), timeout, armed } );
+
+		Some( Self { armed } )
+	}
+
+
+	// An iteration starts now.
+	//
+	pub fn iteration( &self )
+	{
+		ITERATION.store( self.armed.elapsed().as_nanos() as u64, Relaxed );
+	}
+}
+
+
+impl Drop for Watch
+{
+	fn drop( &mut self )
+	{
+		*WATCHED .lock().expect( "lock watched"  ) = None;
+		*PROGRESS.lock().expect( "lock progress" ) = None;
+	}
+}
+
+
+// The timeout from `BENCH_TIMEOUT`, `None` if it is 0.
+//
+fn timeout() -> Option<Duration>
+{
+	let secs = match env::var( TIMEOUT_VAR )
+	{
+		Err( _ ) => return Some( DEFAULT_TIMEOUT ),
+
+		Ok( t ) => match t.parse::<u64>()
+		{
+			Ok ( secs ) => secs,
+			Err( e    ) =>
+			{
+				eprintln!( "Invalid {}: {}: {}, using {:?}", TIMEOUT_VAR, t, e, DEFAULT_TIMEOUT );
+				return Some( DEFAULT_TIMEOUT );
+			}
+		}
+	};
+
+	match secs
+	{
+		0    => None,
+		secs => Some( Duration::from_secs( secs ) ),
+	}
+}
+
+
+fn watchdog()
+{
+	loop
+	{
+		thread::sleep( POLL );
+
+		if let Some( watched ) = &*WATCHED.lock().expect( "lock watched" )
+		{
+			let iteration = watched.armed + Duration::from_nanos( ITERATION.load( Relaxed ) );
+
+			if iteration.elapsed() > watched.timeout
+			{
+				hung( watched );
+			}
+		}
+	}
+}
+
+
+// Report what hung and exit. Runs on the watchdog thread, the benchmark is still stuck.
+//
+fn hung( watched: &Watched ) -> !
+{
+	eprintln!( "\nWatchdog: {} did not finish an iteration within {:?}, aborting.", watched.id, watched.timeout );
+
+	match &*PROGRESS.lock().expect( "lock progress" )
+	{
+		Some( progress ) => eprintln!( "{}", progress ),
+		None             => eprintln!( "No progress recorded, only rings record it." ),
+	}
+
+	process::exit( HUNG_EXIT_CODE );
+}
+
+
+
+// The last message every node of a ring received, so the watchdog can show where it got stuck.
+//
+pub struct Progress
+{
+	end  : usize     ,
+	nodes: Vec<Slot> ,
+}
+
+
+// A counter on it's own cache line.
+//
+#[ repr( align( 64 ) ) ]
+//
+struct Slot( AtomicUsize );
+
+
+impl Progress
+{
+	// Record the progress of a ring, if a watch is armed. It replaces that of a previous ring.
+	//
+	pub fn new( config: &RingConfig ) -> Option< Arc<Self> >
+	{
+		if WATCHED.lock().expect( "lock watched" ).is_none() { return None; }
+
+		let progress = Arc::new( Self
+		{
+			end  : config.end()                                                            ,
+			nodes: ( 0..config.nodes ).map( |_| Slot( AtomicUsize::new( 0 ) ) ).collect() ,
+		});
+
+		*PROGRESS.lock().expect( "lock progress" ) = Some( progress.clone() );
+
+		Some( progress )
+	}
+
+
+	// Node `id` (starting at 1) received `msg`.
+	//
+	pub fn seen( &self, id: usize, msg: usize )
+	{
+		self.nodes[ id - 1 ].0.store( msg, Relaxed );
+	}
+
+
+	// The last message of every node, 0 if it didn't receive any.
+	//
+	pub fn last_seen( &self ) -> Vec<usize>
+	{
+		self.nodes.iter().map( |s| s.0.load( Relaxed ) ).collect()
+	}
+}
+
+
+impl fmt::Display for Progress
+{
+	fn fmt( &self, f: &mut fmt::Formatter<'_> ) -> fmt::Result
+	{
+		writeln!( f, "Last message received per node, the last message is {}:", self.end )?;
+
+		for (i, msg) in self.last_seen().into_iter().enumerate()
+		{
+			match msg
+			{
+				0   => writeln!( f, "  node {:>5}: none", i + 1      )?,
+				msg => writeln!( f, "  node {:>5}: {}"  , i + 1, msg )?,
+			}
+		}
+
+		Ok(())
+	}
+}
+
+
+
+#[ cfg( test ) ]
+//
+mod tests
+{
+	use super::*;
+	use crate::Forward;
+
+	#[test]
+	//
+	fn report()
+	{
+		let config   = RingConfig::new( 3, Forward::Inline ).laps( 2 );
+		let progress = Progress { end: config.end(), nodes: ( 0..3 ).map( |_| Slot( AtomicUsize::new( 0 ) ) ).collect() };
+
+		progress.seen( 1, 4 );
+		progress.seen( 3, 2 );
+
+		assert_eq!( vec![ 4, 0, 2 ], progress.last_seen() );
+
+		assert_eq!
+		(
+			"Last message received per node, the last message is 6:\n  node     1: 4\n  node     2: none\n  node     3: 2\n",
+			progress.to_string(),
+		);
+	}
+}
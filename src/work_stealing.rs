@@ -0,0 +1,266 @@
+//! Work stealing: spawn tasks of uneven durations from one thread and count how many of them every
+//! worker thread ran. A pool that balances the load well spreads them over all its threads, one that
+//! doesn't leaves some threads idle while others are busy, which the total time alone doesn't show.
+//!
+//! Every thread counts the tasks it ran in a thread local counter, which registers itself in a
+//! global list the first time it is used, so the counts of all threads can be read after a run.
+//! `timed` collects the counts per thread on the record of the benchmark.
+//!
+use crate::Record;
+use futures::{ future::join_all, task::{ Spawn, SpawnExt } };
+use serde::{ Deserialize, Serialize };
+use std::{ fmt, sync::{ Arc, Mutex, atomic::{ AtomicU64, Ordering::Relaxed } }, time::{ Duration, Instant } };
+
+
+static COUNTERS: Mutex< Vec< Arc<AtomicU64> > > = Mutex::new( Vec::new() );
+static BALANCE : Mutex< Option<TaskBalance> >   = Mutex::new( None );
+
+
+thread_local!
+{
+	static EXECUTED: Arc<AtomicU64> =
+	{
+		let counter = Arc::new( AtomicU64::new( 0 ) );
+
+		COUNTERS.lock().expect( "lock task counters" ).push( counter.clone() );
+
+		counter
+	};
+}
+
+
+// The tasks run by every thread so far, indexed by the order in which threads ran their first task.
+//
+fn executed() -> Vec<u64>
+{
+	COUNTERS.lock().expect( "lock task counters" ).iter().map( |c| c.load( Relaxed ) ).collect()
+}
+
+
+
+// How many tasks every worker thread ran, busiest thread first. Workers that didn't run any count as 0,
+// so they weigh in on the imbalance.
+//
+#[ derive( Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize ) ]
+//
+pub struct TaskBalance
+{
+	pub tasks_per_thread: Vec<u64>,
+}
+
+
+impl TaskBalance
+{
+	// The busiest thread divided by the mean over all workers. 1 is perfectly balanced.
+	//
+	pub fn imbalance( &self ) -> f64
+	{
+		let total: u64 = self.tasks_per_thread.iter().sum();
+
+		match self.tasks_per_thread.first()
+		{
+			Some( max ) if total > 0 => *max as f64 / ( total as f64 / self.tasks_per_thread.len() as f64 ),
+			_                        => 0.0,
+		}
+	}
+
+
+	// Add the counts of another run, busiest thread to busiest thread.
+	//
+	pub fn merge( &mut self, other: &TaskBalance )
+	{
+		if self.tasks_per_thread.len() < other.tasks_per_thread.len()
+		{
+			self.tasks_per_thread.resize( other.tasks_per_thread.len(), 0 );
+		}
+
+		for (total, tasks) in self.tasks_per_thread.iter_mut().zip( &other.tasks_per_thread )
+		{
+			*total += tasks;
+		}
+	}
+}
+
+
+// Record the counts of the workload that is running.
+//
+pub fn record_balance( balance: &TaskBalance )
+{
+	let mut guard = BALANCE.lock().expect( "lock task balance" );
+
+	match &mut *guard
+	{
+		Some( b ) => b.merge( balance ),
+		None      => *guard = Some( balance.clone() ),
+	}
+}
+
+
+// Take the counts recorded since the last call.
+//
+pub fn take_balance() -> Option<TaskBalance>
+{
+	BALANCE.lock().expect( "lock task balance" ).take()
+}
+
+
+
+pub struct WorkStealing
+{
+	tasks  : usize,
+	workers: usize,
+}
+
+
+impl WorkStealing
+{
+	// Spawn `tasks` tasks per run on a pool with `workers` threads.
+	//
+	pub fn new( tasks: usize, workers: usize ) -> Self
+	{
+		assert!( tasks   > 0 );
+		assert!( workers > 0 );
+
+		Self { tasks, workers }
+	}
+
+
+	pub async fn run( &self, exec: impl Spawn )
+	{
+		let before = executed();
+
+		let handles: Vec<_> = ( 0..self.tasks ).map( |i|
+		{
+			exec.spawn_with_handle( work( duration( i ) ) ).expect( "spawn task" )
+
+		}).collect();
+
+		join_all( handles ).await;
+
+		let after = executed();
+
+		// The counters are per thread in the process, not per worker of this pool, so only the threads
+		// that ran tasks are known to be workers. The others are idle.
+		//
+		let mut tasks_per_thread: Vec<u64> = after.iter().enumerate()
+
+			.map   ( |(i, a)| a - before.get( i ).copied().unwrap_or( 0 ) )
+			.filter( |n| *n > 0 )
+			.collect()
+		;
+
+		tasks_per_thread.sort_unstable_by( |a, b| b.cmp( a ) );
+
+		if tasks_per_thread.len() < self.workers
+		{
+			tasks_per_thread.resize( self.workers, 0 );
+		}
+
+		record_balance( &TaskBalance { tasks_per_thread } );
+	}
+}
+
+
+// Task durations go from 1µs to 128µs, doubling every task, so a long task regularly lands on a busy
+// thread.
+//
+fn duration( task: usize ) -> Duration
+{
+	Duration::from_micros( 1 << ( task % 8 ) )
+}
+
+
+async fn work( duration: Duration )
+{
+	let start = Instant::now();
+
+	while start.elapsed() < duration {}
+
+	EXECUTED.with( |e| e.fetch_add( 1, Relaxed ) );
+}
+
+
+
+// Prints a table of the tasks per thread in a set of records. Records without counts are skipped.
+//
+pub struct BalanceReport( pub Vec<( String, TaskBalance )> );
+
+
+impl BalanceReport
+{
+	pub fn new( records: &[Record] ) -> Self
+	{
+		Self( records.iter().filter_map( |r| Some(( r.id.to_string(), r.balance.clone()? )) ).collect() )
+	}
+
+
+	pub fn is_empty( &self ) -> bool
+	{
+		self.0.is_empty()
+	}
+}
+
+
+impl fmt::Display for BalanceReport
+{
+	fn fmt( &self, f: &mut fmt::Formatter<'_> ) -> fmt::Result
+	{
+		let width = self.0.iter().map( |(id, _)| id.len() ).max().unwrap_or( 0 );
+
+		writeln!( f, "Tasks per thread, busiest first:" )?;
+		writeln!( f )?;
+		writeln!( f, "  {:<width$}  {:>9}  {:>8}  tasks", "", "imbalance", "threads", width = width )?;
+
+		for (id, b) in &self.0
+		{
+			writeln!
+			(
+				f, "  {:<width$}  {:>9.2}  {:>8}  {:?}",
+				id, b.imbalance(), b.tasks_per_thread.len(), b.tasks_per_thread, width = width,
+			)?;
+		}
+
+		Ok(())
+	}
+}
+
+
+
+#[ cfg( test ) ]
+//
+mod tests
+{
+	use super::*;
+	use futures::executor::{ ThreadPool, block_on };
+
+	#[test]
+	//
+	fn imbalance()
+	{
+		let mut balance = TaskBalance { tasks_per_thread: vec![ 6, 2 ] };
+
+		assert_eq!( 1.5, balance.imbalance() );
+		assert_eq!( 3.0, TaskBalance { tasks_per_thread: vec![ 6, 2, 0, 0 ] }.imbalance() );
+
+		balance.merge( &TaskBalance { tasks_per_thread: vec![ 2, 2, 2 ] } );
+
+		assert_eq!( vec![ 8, 4, 2 ], balance.tasks_per_thread );
+		assert_eq!( 0.0, TaskBalance::default().imbalance() );
+	}
+
+
+	#[test]
+	//
+	fn every_task_counted()
+	{
+		let pool = ThreadPool::builder().pool_size( 2 ).create().expect( "create threadpool" );
+
+		take_balance();
+		block_on( WorkStealing::new( 50, 2 ).run( pool ) );
+
+		let balance = take_balance().expect( "balance recorded" );
+
+		assert_eq!( 50, balance.tasks_per_thread.iter().sum::<u64>() );
+		assert_eq!( 2 , balance.tasks_per_thread.len() );
+	}
+}